@@ -0,0 +1,113 @@
+// Times identify_signal_candles against the earlier two-pass labeling (date map, then a
+// second walk applying it) on ten years of one-minute bars. Run with
+// `cargo bench --bench signal_candles`.
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use orb::{NiftyStrategy, OhlcBar};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const DAYS: usize = 2500;
+const RUNS: usize = 16;
+
+fn minute_bars() -> Vec<OhlcBar> {
+    let first_day = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+    let open = NaiveTime::from_hms_opt(9, 15, 0).unwrap();
+    let mut bars = Vec::with_capacity(DAYS * 375);
+    for day in 0..DAYS {
+        let date = first_day + chrono::Duration::days(day as i64);
+        for minute in 0..375 {
+            let datetime = NaiveDateTime::new(date, open) + chrono::Duration::minutes(minute);
+            let price = 100.0 + ((day * 7 + minute as usize) % 13) as f64;
+            let close = if (day + minute as usize).is_multiple_of(2) { price + 0.5 } else { price - 0.5 };
+            bars.push(OhlcBar {
+                datetime,
+                date,
+                time: datetime.time(),
+                open: price,
+                high: price + 1.0,
+                low: price - 1.0,
+                close,
+                volume: 1000.0,
+                candle_type: None,
+                candle_val: None,
+                candle_high: None,
+                candle_low: None,
+                candle_body_ratio: None,
+                wick_ratio: None,
+                source_count: 1,
+                synthetic: false,
+                session_high: price + 1.0,
+                session_low: price - 1.0,
+                atr: None,
+                signal: 0,
+            });
+        }
+    }
+    bars
+}
+
+// The labeling before the single-pass rewrite, writing the same fields as the current
+// implementation so only the pass structure differs
+fn two_pass(data: &mut [OhlcBar], target_time: NaiveTime) {
+    let mut signal_map: HashMap<NaiveDate, (String, f64, f64, f64, f64)> = HashMap::new();
+    for bar in data.iter().filter(|bar| bar.time == target_time) {
+        let (candle_type, candle_val) = if bar.close > bar.open {
+            ("bullish".to_string(), bar.high)
+        } else {
+            ("bearish".to_string(), bar.low)
+        };
+        let body_ratio = (bar.close - bar.open).abs() / (bar.high - bar.low);
+        signal_map.entry(bar.date).or_insert((candle_type, candle_val, bar.high, bar.low, body_ratio));
+    }
+    for bar in data.iter_mut() {
+        let signal = signal_map.get(&bar.date);
+        bar.candle_body_ratio = signal.map(|s| s.4);
+        bar.candle_type = signal.map(|s| s.0.clone());
+        bar.candle_val = signal.map(|s| s.1);
+        bar.candle_high = signal.map(|s| s.2);
+        bar.candle_low = signal.map(|s| s.3);
+        bar.signal = 0;
+    }
+}
+
+fn median(mut samples: Vec<Duration>) -> Duration {
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+fn main() {
+    let bars = minute_bars();
+    let target_time = NiftyStrategy::new().config.signal_time;
+
+    // Build both inputs up front and swap which one runs first on every run; the second
+    // of a pair is consistently slower as the first one's allocations are still live
+    let mut single_pass = Vec::with_capacity(RUNS);
+    let mut reference = Vec::with_capacity(RUNS);
+    for run in 0..RUNS {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(bars.clone());
+        let mut data = bars.clone();
+        let mut time_single_pass = || {
+            let start = Instant::now();
+            strategy.identify_signal_candles().unwrap();
+            single_pass.push(start.elapsed());
+        };
+        let mut time_reference = || {
+            let start = Instant::now();
+            two_pass(&mut data, target_time);
+            reference.push(start.elapsed());
+        };
+        if run.is_multiple_of(2) {
+            time_single_pass();
+            time_reference();
+        } else {
+            time_reference();
+            time_single_pass();
+        }
+    }
+    let (single_pass, reference) = (median(single_pass), median(reference));
+
+    println!("{} bars over {} days, median of {} runs", bars.len(), DAYS, RUNS);
+    println!("two-pass:    {:>8.2} ms", reference.as_secs_f64() * 1000.0);
+    println!("single-pass: {:>8.2} ms", single_pass.as_secs_f64() * 1000.0);
+}
@@ -0,0 +1,5154 @@
+// Opening-range breakout backtester; main.rs is a thin CLI over this library
+
+use csv::ReaderBuilder;
+use chrono::{Datelike, FixedOffset, NaiveDate, NaiveTime, NaiveDateTime, TimeZone, Timelike};
+use std::time::Instant;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone)]
+struct CsvRow {
+    date: String,  // This will contain "2015-01-09 09:15:00" format
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    // Some free data sources have no volume column at all
+    #[serde(default)]
+    volume: f64,
+}
+
+// CSV header names holding each CsvRow field, for exports that don't use the default names
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub date: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            date: "date".to_string(),
+            open: "open".to_string(),
+            high: "high".to_string(),
+            low: "low".to_string(),
+            close: "close".to_string(),
+            volume: "volume".to_string(),
+        }
+    }
+}
+
+impl ColumnMapping {
+    // Renames the file's headers to CsvRow field names so rows deserialize by name.
+    // Unmapped columns are blanked and ignored; volume is the only optional column.
+    fn logical_headers(&self, headers: &csv::StringRecord) -> Result<csv::StringRecord> {
+        let fields = [
+            ("date", &self.date),
+            ("open", &self.open),
+            ("high", &self.high),
+            ("low", &self.low),
+            ("close", &self.close),
+            ("volume", &self.volume),
+        ];
+        for (field, column) in &fields[..5] {
+            if !headers.iter().any(|h| h == column.as_str()) {
+                anyhow::bail!("CSV is missing the '{}' column required for the {} field", column, field);
+            }
+        }
+        Ok(headers.iter()
+            .map(|h| fields.iter().find(|(_, column)| column.as_str() == h).map_or("", |(field, _)| field))
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OhlcBar {
+    pub datetime: NaiveDateTime,
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub candle_type: Option<String>,
+    pub candle_val: Option<f64>,
+    pub candle_high: Option<f64>,
+    pub candle_low: Option<f64>,
+    // Signal candle body as a fraction of its high-low range
+    pub candle_body_ratio: Option<f64>,
+    // Longer of the signal candle's rejection wicks as a fraction of its range, set on the
+    // day's bars by the wick signal rule
+    pub wick_ratio: Option<f64>,
+    // Source rows aggregated into this bar; fewer than expected indicates a data gap
+    pub source_count: usize,
+    // Flat placeholder inserted by fill_gaps for a missing interval; no real trading behind it
+    pub synthetic: bool,
+    // Highest high and lowest low of the session up to and including this bar
+    pub session_high: f64,
+    pub session_low: f64,
+    // Wilder average true range over atr_period bars ending here; None during warm-up
+    pub atr: Option<f64>,
+    pub signal: i32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Instrument {
+    pub symbol: String,
+    pub tick_size: f64,
+    pub lot_size: f64,
+    pub point_value: f64,
+    pub cost_bps: f64,
+}
+
+// Split or dividend effective at the open of `date`; prices before it are multiplied by
+// `factor`, e.g. 0.5 for a 2:1 split
+#[derive(Debug, Clone, Deserialize)]
+struct CorporateAction {
+    date: NaiveDate,
+    factor: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ExitReason {
+    // Closed at the open of the 15:15 bar (or the last bar of the day)
+    EndOfDay,
+    StopLoss,
+    TakeProfit,
+    // Still short of min_progress_points once dead_trade_minutes had passed
+    NoProgress,
+    // Still held when the data ran out; there is no exit yet
+    Open,
+}
+
+// Where a position is opened: the fill bar's index within the day, the direction and the
+// raw (pre-spread) fill price
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    idx: usize,
+    signal: i32,
+    price: f64,
+}
+
+// OHLC and volume of a bar, kept on trades for journaling
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct BarOhlcv {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl From<&OhlcBar> for BarOhlcv {
+    fn from(bar: &OhlcBar) -> Self {
+        Self { open: bar.open, high: bar.high, low: bar.low, close: bar.close, volume: bar.volume }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub entry_time: NaiveDateTime,
+    pub entry_price: f64,
+    // None while the position is open
+    pub exit_time: Option<NaiveDateTime>,
+    pub exit_price: Option<f64>,
+    pub exit_reason: ExitReason,
+    pub quantity: f64,
+    pub point_value: f64,
+    pub signal: i32,
+    pub gross_pnl: f64,
+    pub net_pnl: f64,
+    // Maximum favorable excursion in points and the bar offset from entry where it peaked
+    pub mfe: f64,
+    pub bars_to_peak: usize,
+    // Bars from the entry bar to the exit bar
+    pub bars_held: usize,
+    // Still held when the data ran out: the exit fields are None, exit_reason is Open and PnL
+    // is zero until the position closes
+    pub is_open: bool,
+    // Label from the strategy's tagger, if any
+    pub tag: Option<String>,
+    pub entry_bar_ohlcv: BarOhlcv,
+    pub exit_bar_ohlcv: Option<BarOhlcv>,
+}
+
+impl Trade {
+    // Net PnL as a fraction of the entry notional. Shorts use the same entry base, so
+    // (entry - exit) / entry before costs; the notional's magnitude keeps the sign of the
+    // PnL even for instruments trading below zero.
+    pub fn return_pct(&self) -> f64 {
+        self.net_pnl / (self.entry_price * self.quantity * self.point_value).abs()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub total_pnl: f64,
+    pub max_drawdown: f64,
+    pub sharpe_ratio: f64,
+    // Mean PnL over the standard deviation of the losing trades only
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+    pub win_rate: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64,
+    pub exposure_pct: f64,
+    pub avg_bars_to_peak: f64,
+    // Trades profitable before costs but losing after them
+    pub cost_flipped_trades: usize,
+    // Average net return per trade in basis points of the entry notional
+    pub expectancy_bps: f64,
+    // Share of trading days whose combined net PnL was positive
+    pub profitable_days_pct: f64,
+    // Compound annual growth of the per-trade percent returns, and its ratio to the
+    // compounded max drawdown; both 0.0 for spans under a year
+    pub cagr_pct: f64,
+    pub mar_ratio: f64,
+    // Net PnL relative to initial_capital, in total and annualized over the calendar days from
+    // the first to the last trade; both 0.0 without a starting capital
+    pub total_return_pct: f64,
+    pub capital_cagr_pct: f64,
+    // Longest runs of winning and losing trades; break-even trades end both
+    pub max_consecutive_wins: usize,
+    pub max_consecutive_losses: usize,
+    pub total_trades: usize,
+}
+
+impl PerformanceMetrics {
+    // Degenerate inputs (zero entry prices, NaN fills) can leave NaN or infinite figures that
+    // would poison downstream sums and JSON; report each as 0.0 with a warning instead
+    fn clamp_non_finite(mut self) -> Self {
+        let fields = [
+            ("total_pnl", &mut self.total_pnl),
+            ("max_drawdown", &mut self.max_drawdown),
+            ("sharpe_ratio", &mut self.sharpe_ratio),
+            ("sortino_ratio", &mut self.sortino_ratio),
+            ("calmar_ratio", &mut self.calmar_ratio),
+            ("win_rate", &mut self.win_rate),
+            ("avg_win", &mut self.avg_win),
+            ("avg_loss", &mut self.avg_loss),
+            ("exposure_pct", &mut self.exposure_pct),
+            ("avg_bars_to_peak", &mut self.avg_bars_to_peak),
+            ("expectancy_bps", &mut self.expectancy_bps),
+            ("profitable_days_pct", &mut self.profitable_days_pct),
+            ("cagr_pct", &mut self.cagr_pct),
+            ("mar_ratio", &mut self.mar_ratio),
+            ("total_return_pct", &mut self.total_return_pct),
+            ("capital_cagr_pct", &mut self.capital_cagr_pct),
+        ];
+        for (name, value) in fields {
+            if !value.is_finite() {
+                eprintln!("Warning: {} came out as {}; reporting 0.0", name, value);
+                *value = 0.0;
+            }
+        }
+        self
+    }
+}
+
+// Everything save_results_json writes: timestamps as ISO-8601 strings, PnL as numbers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResultsDocument {
+    pub trades: Vec<Trade>,
+    pub metrics: PerformanceMetrics,
+}
+
+// Data quality summary of the loaded bars, printed before any trading logic runs
+#[derive(Debug)]
+pub struct DataHealthReport {
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub trading_days: usize,
+    pub avg_bars_per_day: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+    // CSV rows whose timestamp could not be parsed
+    pub rejected_rows: usize,
+    // Intraday spacings between consecutive bars wider than one bar interval
+    pub gaps: usize,
+}
+
+impl std::fmt::Display for DataHealthReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Data health: {} to {} ({} trading days)", self.first_date, self.last_date, self.trading_days)?;
+        writeln!(f, "  Bars per day: {:.1}", self.avg_bars_per_day)?;
+        writeln!(f, "  Price range: {:.2} - {:.2}", self.min_price, self.max_price)?;
+        write!(f, "  Rejected rows: {}, intraday gaps: {}", self.rejected_rows, self.gaps)
+    }
+}
+
+// Closed trades sharing one exit reason, e.g. to compare how fast stops fire
+#[derive(Debug)]
+pub struct ExitReasonStats {
+    pub trades: usize,
+    pub avg_bars_held: f64,
+    pub total_pnl: f64,
+}
+
+// Worst move against the open position within each trading day, in PnL units
+#[derive(Debug)]
+pub struct SessionAdverseExcursion {
+    pub by_day: BTreeMap<NaiveDate, f64>,
+    pub average: f64,
+    pub worst: f64,
+}
+
+// Factsheet figures, all in percent of initial_capital over the first-to-last trade span.
+// Volatility annualizes the daily return deviation by sqrt(252) trading days; Sharpe (with
+// no risk-free rate) and Calmar divide the annualized return by volatility and drawdown.
+#[derive(Debug)]
+pub struct AnnualizedReport {
+    pub total_return_pct: f64,
+    pub annualized_return_pct: f64,
+    pub annualized_volatility_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub sharpe_ratio: f64,
+    pub calmar_ratio: f64,
+}
+
+// One in-sample/out-of-sample step of a walk-forward run; `params` indexes the grid
+#[derive(Debug)]
+pub struct WalkForwardWindow {
+    pub in_sample: (NaiveDate, NaiveDate),
+    pub out_of_sample: (NaiveDate, NaiveDate),
+    pub params: usize,
+    pub metrics: PerformanceMetrics,
+}
+
+#[derive(Debug)]
+pub struct WalkForwardResult {
+    pub windows: Vec<WalkForwardWindow>,
+    // Cumulative net PnL over the out-of-sample trades, starting at 0.0
+    pub equity_curve: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SignalSource {
+    // Close beyond the 9:25 signal candle's high (bullish) or low (bearish)
+    #[default]
+    SignalCandle,
+    // Close beyond the prior bar's high (long) or low (short) within the session
+    MomentumBreakout,
+    // Reversal off a signal candle whose lower (long) or upper (short) wick exceeds
+    // min_wick_ratio of its range; entered on the first bar after it
+    WickRejection,
+}
+
+// Classification of a signal candle whose close equals its open
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DojiPolicy {
+    Bullish,
+    #[default]
+    Bearish,
+    // No signal candle, so no trade that day
+    Skip,
+}
+
+// What to do when price breaks the signal candle on the side opposite its color
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MixedDayPolicy {
+    #[default]
+    NoTrade,
+    // Trade in the candle's direction, against the breakout
+    Fade,
+}
+
+// Basis for transaction_cost_rate when the symbol has no instrument metadata
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CostModel {
+    // Default, kept so earlier backtests reproduce: rate times the price move between the legs
+    #[default]
+    PriceMove,
+    // Rate charged on the traded value of each leg, as brokerage actually is
+    Notional,
+}
+
+// Adverse fill slippage on each leg: longs pay up on entry and receive less on exit,
+// shorts the reverse
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SlippageModel {
+    #[default]
+    None,
+    FixedPoints(f64),
+    // Percent of the fill price, e.g. 0.05 for 5 bps
+    PercentOfPrice(f64),
+}
+
+impl SlippageModel {
+    // Slippage in points for a fill at `price`
+    pub fn points(self, price: f64) -> f64 {
+        match self {
+            SlippageModel::None => 0.0,
+            SlippageModel::FixedPoints(points) => points,
+            SlippageModel::PercentOfPrice(pct) => price.abs() * pct / 100.0,
+        }
+    }
+}
+
+// Higher-timeframe bars, e.g. for trend filters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarPeriod {
+    // One bar per trading session
+    Daily,
+    // One bar per Monday-Friday week, however many sessions it had
+    Weekly,
+}
+
+// How an aggregated bar's volume is derived from its constituents
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum VolumeAggregation {
+    #[default]
+    Sum,
+    // Final constituent's volume, for feeds reporting running session volume
+    Last,
+}
+
+// Which trades form the win rate denominator
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum WinRateBasis {
+    #[default]
+    AllTrades,
+    // Break-even trades count as neither wins nor losses and are left out
+    NonScratchTrades,
+}
+
+// Thousands separators for money in the printed summary
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DigitGrouping {
+    #[default]
+    None,
+    // 1,234,567.89
+    Standard,
+    // 12,34,567.89 (lakh/crore)
+    Indian,
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+
+// How a confirmed breakout is entered
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EntryMode {
+    // At the close of the breakout bar
+    #[default]
+    Breakout,
+    // At the signal level once price pulls back to it; no trade if it never does
+    Retest,
+}
+
+// Which exit fills when one bar trades through both the stop and the target
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ExitPrecedence {
+    // Conservative: assume the stop was reached first
+    #[default]
+    StopFirst,
+    TargetFirst,
+}
+
+// Response to a data problem found while loading
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DataIssuePolicy {
+    // Print a warning and keep going
+    #[default]
+    Warn,
+    // Fail the load
+    Error,
+}
+
+// What to do with a setup whose stop risk exceeds max_risk_per_trade
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RiskCapPolicy {
+    // Shrink the quantity until the risk fits the cap
+    ReduceSize,
+    #[default]
+    Skip,
+}
+
+// How the protective stop level is derived
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum StopBasis {
+    // stop_loss_points away from the entry price
+    #[default]
+    Points,
+    // The signal candle's low for longs, high for shorts
+    SignalCandleExtreme,
+}
+
+// Which edge of a bar its timestamp refers to in output
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TimestampConvention {
+    #[default]
+    BarStart,
+    // Start + interval: when the bar completed and its signal became actionable
+    BarClose,
+}
+
+// Cost as a fraction of the traded price move, used when no instrument metadata is loaded
+const DEFAULT_TRANSACTION_COST_RATE: f64 = 0.0012;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategyConfig {
+    // Symbol traded by this run, used to look up instrument metadata
+    pub symbol: String,
+    // Input CSV header names
+    pub columns: ColumnMapping,
+    // Multiplier applied to loaded OHLC, e.g. 0.01 for feeds quoting integer ticks of 0.01
+    pub price_scale: f64,
+    // Width of the aggregated bars; must evenly divide an hour
+    pub timeframe_minutes: u32,
+    // Input is already at timeframe_minutes: load its rows as bars without re-bucketing them
+    pub skip_aggregation: bool,
+    // Forward-fill missing intraday bars with flat synthetic bars at the prior close
+    pub fill_gaps: bool,
+    // Files loaded together that disagree on a date's signal candle
+    pub duplicate_signal_policy: DataIssuePolicy,
+    // Drop rows repeating an earlier row's timestamp and OHLC, which would otherwise inflate
+    // volume. More than max_duplicate_rows such rows triggers duplicate_rows_policy.
+    pub dedupe_rows: bool,
+    pub max_duplicate_rows: Option<usize>,
+    pub duplicate_rows_policy: DataIssuePolicy,
+    pub volume_agg: VolumeAggregation,
+    // Start time of the opening-range signal candle
+    pub signal_time: NaiveTime,
+    // Rule used by generate_trading_signals to arm entries
+    pub signal_source: SignalSource,
+    pub entry_mode: EntryMode,
+    pub mixed_day_policy: MixedDayPolicy,
+    // Only trade days whose signal candle color is the opposite of the prior day's
+    pub require_candle_flip: bool,
+    pub doji_as: DojiPolicy,
+    // Signal candles whose body is below this many points are labeled "doji" and arm no trade;
+    // 0.0 leaves only exact dojis, which doji_as classifies
+    pub doji_body_threshold: f64,
+    // Skip days whose signal candle body is below this fraction of its range
+    pub min_body_ratio: Option<f64>,
+    // Rejection wick fraction of the signal candle's range needed by the WickRejection rule
+    pub min_wick_ratio: f64,
+    // Trading days at each end of the data excluded from trading, as they are often partial
+    pub skip_first_n_days: usize,
+    pub skip_last_n_days: usize,
+    // Units traded per position; PnL and costs scale with it
+    pub quantity: f64,
+    // Full bid-ask spread in points; each fill crosses half of it
+    pub spread_points: f64,
+    pub slippage: SlippageModel,
+    // Cost rate used when the symbol has no instrument metadata; 0.0 for a frictionless run
+    pub transaction_cost_rate: f64,
+    pub cost_model: CostModel,
+    // Protective stop distance in points from the entry price
+    pub stop_loss_points: Option<f64>,
+    pub stop_basis: StopBasis,
+    // (trigger, stop) pairs in R, the initial stop distance: once the trade has run trigger R
+    // in its favor the stop ratchets to stop R from entry, e.g. (1.0, 0.0) locks breakeven
+    pub r_based_trailing: Vec<(f64, f64)>,
+    // Profit target distance in points from the entry price
+    pub take_profit_points: Option<f64>,
+    // Target at this many ATRs from entry, using the entry bar's ATR; with take_profit_points
+    // also set, the nearer target applies
+    pub atr_target_multiple: Option<f64>,
+    pub atr_period: usize,
+    pub exit_precedence: ExitPrecedence,
+    // Largest loss to the stop allowed per trade, in PnL units
+    pub max_risk_per_trade: Option<f64>,
+    pub risk_cap_policy: RiskCapPolicy,
+    // Stop opening trades while the running drawdown exceeds this, in PnL units. Skipped
+    // trades are still tracked on paper; with drawdown_resume set, trading restarts once the
+    // paper drawdown recovers to that level.
+    pub max_drawdown_halt: Option<f64>,
+    pub drawdown_resume: Option<f64>,
+    // Minutes after entry before stop/target/trailing exits are checked
+    pub stop_activation_delay_minutes: i64,
+    // Give up on a trade still short of min_progress_points in its favor this long after entry
+    pub dead_trade_minutes: Option<i64>,
+    pub min_progress_points: f64,
+    // Entries allowed per session; after an exit the next fresh signal may re-enter
+    pub max_trades_per_day: usize,
+    // Re-entries need the close to pull back inside the level and break out again
+    pub require_rearm: bool,
+    // Leave a position open, rather than force-closing it, when the data ends mid-session
+    pub allow_open_trade_at_end: bool,
+    // Order trades by full entry timestamp instead of by date alone
+    pub sort_by_entry_time: bool,
+    // Cap each fill at this fraction of the fill bar's volume
+    pub max_volume_participation: Option<f64>,
+    // Exchange session hours, used as the denominator for exposure
+    pub session_start: NaiveTime,
+    pub session_end: NaiveTime,
+    // Midday pauses excluded from holding time and session length
+    pub session_breaks: Vec<(NaiveTime, NaiveTime)>,
+    // Time at which a new trading day starts, e.g. 17:00 for futures sessions that open the
+    // evening before; bars at or after it belong to the next day. Midnight keeps calendar days.
+    pub day_reset_time: NaiveTime,
+    // Skip performance metrics in run() when only the trades file is needed
+    pub compute_metrics: bool,
+    pub win_rate_basis: WinRateBasis,
+    // Delta degrees of freedom for the Sharpe and Sortino deviations: 0 divides by n
+    // (population), 1 by n - 1 (sample), which widens the deviation and shrinks both ratios
+    // for small trade counts
+    pub std_ddof: usize,
+    // Trailing window for the MAR ratio, ending at the last trade; None uses every trade
+    pub mar_period_years: Option<f64>,
+    // Starting account size for total return and CAGR; 0.0 leaves both unreported
+    pub initial_capital: f64,
+    pub timestamp_convention: TimestampConvention,
+    // Append the entry and exit bars' OHLCV to each row of the trades file
+    pub extended_output: bool,
+    // Fixed offset appended to saved entry/exit times (RFC3339); None keeps naive timestamps
+    #[serde(with = "utc_offset_serde")]
+    pub output_utc_offset: Option<FixedOffset>,
+    // Applied to PnL figures in the printed summary, e.g. "₹" with Indian grouping
+    pub currency_symbol: String,
+    pub digit_grouping: DigitGrouping,
+    // Decimal places for ratios (Sharpe, Sortino, Calmar, MAR) and PnL amounts in the summary
+    pub ratio_precision: usize,
+    pub pnl_precision: usize,
+}
+
+impl StrategyConfig {
+    // Reads a TOML config; omitted keys keep their defaults
+    pub fn load(config_path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(config_path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn save(&self, config_path: &str) -> Result<()> {
+        std::fs::write(config_path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+// Offsets are stored in their "+05:30" display form
+mod utc_offset_serde {
+    use chrono::FixedOffset;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(offset: &Option<FixedOffset>, serializer: S) -> Result<S::Ok, S::Error> {
+        match offset {
+            Some(offset) => serializer.serialize_some(&offset.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<FixedOffset>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| s.parse().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "NIFTY".to_string(),
+            columns: ColumnMapping::default(),
+            price_scale: 1.0,
+            timeframe_minutes: 5,
+            skip_aggregation: false,
+            fill_gaps: false,
+            duplicate_signal_policy: DataIssuePolicy::default(),
+            dedupe_rows: false,
+            max_duplicate_rows: None,
+            duplicate_rows_policy: DataIssuePolicy::default(),
+            volume_agg: VolumeAggregation::default(),
+            signal_time: NaiveTime::from_hms_opt(9, 25, 0).unwrap(),
+            signal_source: SignalSource::default(),
+            entry_mode: EntryMode::default(),
+            mixed_day_policy: MixedDayPolicy::default(),
+            require_candle_flip: false,
+            doji_as: DojiPolicy::default(),
+            doji_body_threshold: 0.0,
+            min_body_ratio: None,
+            min_wick_ratio: 0.6,
+            skip_first_n_days: 0,
+            skip_last_n_days: 0,
+            quantity: 1.0,
+            spread_points: 0.0,
+            slippage: SlippageModel::default(),
+            transaction_cost_rate: DEFAULT_TRANSACTION_COST_RATE,
+            cost_model: CostModel::default(),
+            stop_loss_points: None,
+            stop_basis: StopBasis::default(),
+            r_based_trailing: Vec::new(),
+            take_profit_points: None,
+            atr_target_multiple: None,
+            atr_period: 14,
+            exit_precedence: ExitPrecedence::default(),
+            max_risk_per_trade: None,
+            risk_cap_policy: RiskCapPolicy::default(),
+            max_drawdown_halt: None,
+            drawdown_resume: None,
+            stop_activation_delay_minutes: 0,
+            dead_trade_minutes: None,
+            min_progress_points: 0.0,
+            max_trades_per_day: 1,
+            require_rearm: false,
+            allow_open_trade_at_end: false,
+            sort_by_entry_time: false,
+            max_volume_participation: None,
+            session_start: NaiveTime::from_hms_opt(9, 15, 0).unwrap(),
+            session_end: NaiveTime::from_hms_opt(15, 30, 0).unwrap(),
+            session_breaks: Vec::new(),
+            day_reset_time: NaiveTime::MIN,
+            compute_metrics: true,
+            win_rate_basis: WinRateBasis::default(),
+            std_ddof: 0,
+            mar_period_years: None,
+            initial_capital: 0.0,
+            timestamp_convention: TimestampConvention::default(),
+            extended_output: false,
+            output_utc_offset: None,
+            currency_symbol: String::new(),
+            digit_grouping: DigitGrouping::default(),
+            ratio_precision: 4,
+            pnl_precision: 2,
+        }
+    }
+}
+
+// Pipeline progress. Each stage needs the previous one to have run:
+// load_and_prepare_data -> identify_signal_candles -> generate_trading_signals -> identify_trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PipelineStage {
+    Empty,
+    Loaded,
+    CandlesIdentified,
+    SignalsGenerated,
+    TradesIdentified,
+}
+
+// User-supplied label for a trade, given the trade and its entry bar
+pub type TradeTagger = Box<dyn Fn(&Trade, &OhlcBar) -> Option<String>>;
+
+pub struct NiftyStrategy {
+    pub config: StrategyConfig,
+    stage: PipelineStage,
+    instruments: HashMap<String, Instrument>,
+    // Back-adjustments applied to prices by subsequent loads
+    adjustments: Vec<CorporateAction>,
+    // Signals or days removed by each filter during the last run
+    pub filter_drops: BTreeMap<&'static str, usize>,
+    // False when the loaded data had no volume column
+    has_volume: bool,
+    // CSV rows dropped by the last load for an unparseable timestamp
+    rejected_rows: usize,
+    // Labels each new trade from the trade and its entry bar, for metrics_by_tag
+    pub tagger: Option<TradeTagger>,
+    pub data: Vec<OhlcBar>,
+    pub trades: Vec<Trade>,
+}
+
+impl Default for NiftyStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NiftyStrategy {
+    pub fn new() -> Self {
+        Self::with_config(StrategyConfig::default())
+    }
+
+    pub fn with_config(config: StrategyConfig) -> Self {
+        Self {
+            config,
+            stage: PipelineStage::Empty,
+            instruments: HashMap::new(),
+            adjustments: Vec::new(),
+            filter_drops: BTreeMap::new(),
+            has_volume: true,
+            rejected_rows: 0,
+            tagger: None,
+            data: Vec::new(),
+            trades: Vec::new(),
+        }
+    }
+
+    pub fn load_and_prepare_data(&mut self, csv_path: &str) -> Result<()> {
+        let step_start = Instant::now();
+        
+        // Read CSV file, decompressing gzip input on the fly
+        let file = File::open(csv_path).with_context(|| format!("cannot open input file '{}'", csv_path))?;
+        let mut file = BufReader::new(file);
+        if csv_path.ends_with(".gz") || file.fill_buf()?.starts_with(GZIP_MAGIC) {
+            self.load_from_reader(GzDecoder::new(file))?;
+        } else {
+            self.load_from_reader(file)?;
+        }
+        
+        eprintln!("Data loading completed in {:.2} seconds", step_start.elapsed().as_secs_f64());
+        eprintln!("Created {} {}-minute bars", self.data.len(), self.config.timeframe_minutes);
+        if let Some(report) = self.data_health_report() {
+            eprintln!("{}", report);
+        }
+        Ok(())
+    }
+
+    // Loads several CSV files (e.g. one per year) as one series. Where files overlap, the
+    // earliest file's bars win. Returns the (symbol, date) pairs whose signal candle differed
+    // between files, after warning or failing per duplicate_signal_policy.
+    pub fn load_and_prepare_files(&mut self, csv_paths: &[&str]) -> Result<Vec<(String, NaiveDate)>> {
+        let mut per_file: Vec<Vec<OhlcBar>> = Vec::new();
+        let mut rejected_rows = 0;
+        let mut has_volume = true;
+        for path in csv_paths {
+            let mut part = NiftyStrategy::with_config(self.config.clone());
+            part.adjustments = self.adjustments.clone();
+            part.load_and_prepare_data(path)?;
+            rejected_rows += part.rejected_rows;
+            has_volume &= part.has_volume;
+            per_file.push(part.data);
+        }
+        
+        let duplicates = Self::conflicting_signal_days(&self.config.symbol, self.config.signal_time, &per_file);
+        if !duplicates.is_empty() {
+            let dates: Vec<String> = duplicates.iter().map(|(_, date)| date.to_string()).collect();
+            let message = format!(
+                "{} has differing signal candles across input files on {}",
+                self.config.symbol, dates.join(", ")
+            );
+            match self.config.duplicate_signal_policy {
+                DataIssuePolicy::Warn => eprintln!("Warning: {}; keeping the first file's bars", message),
+                DataIssuePolicy::Error => anyhow::bail!(message),
+            }
+        }
+        
+        let mut merged: Vec<OhlcBar> = per_file.into_iter().flatten().collect();
+        merged.sort_by_key(|bar| bar.datetime);
+        merged.dedup_by_key(|bar| bar.datetime);
+        self.load_bars(merged);
+        self.rejected_rows = rejected_rows;
+        self.has_volume = has_volume;
+        Ok(duplicates)
+    }
+
+    // Dates whose signal-time bar appears in more than one file with different OHLC
+    fn conflicting_signal_days(symbol: &str, signal_time: NaiveTime, per_file: &[Vec<OhlcBar>]) -> Vec<(String, NaiveDate)> {
+        let mut candles: BTreeMap<NaiveDate, (f64, f64, f64, f64)> = BTreeMap::new();
+        let mut conflicts: BTreeSet<NaiveDate> = BTreeSet::new();
+        for bars in per_file {
+            for bar in bars.iter().filter(|bar| bar.time == signal_time) {
+                let ohlc = (bar.open, bar.high, bar.low, bar.close);
+                if *candles.entry(bar.date).or_insert(ohlc) != ohlc {
+                    conflicts.insert(bar.date);
+                }
+            }
+        }
+        conflicts.into_iter().map(|date| (symbol.to_string(), date)).collect()
+    }
+
+    // Loads CSV content already in memory, e.g. received over the network
+    pub fn load_from_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.load_from_reader(std::io::Cursor::new(bytes))
+    }
+
+    // Shared parsing and aggregation path for any CSV source
+    fn load_from_reader<R: Read>(&mut self, source: R) -> Result<()> {
+        // Some exports prefix the header with a UTF-8 BOM, which would corrupt the `date` column name
+        let mut source = BufReader::new(source);
+        if source.fill_buf()?.starts_with(UTF8_BOM) {
+            source.consume(UTF8_BOM.len());
+        }
+        
+        let minutes = self.config.timeframe_minutes;
+        if minutes == 0 || 60 % minutes != 0 {
+            anyhow::bail!("timeframe_minutes must be a divisor of 60 (1, 2, 3, 5, 10, 15, 30 or 60), got {}", minutes);
+        }
+        
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(csv::Trim::All)
+            .from_reader(source);
+        
+        let headers = self.config.columns.logical_headers(reader.headers()?)?;
+        self.has_volume = headers.iter().any(|h| h == "volume");
+        if !self.has_volume {
+            eprintln!("Warning: no volume column; volumes are treated as 0 and max_volume_participation is ignored");
+        }
+        
+        let mut raw_data: Vec<CsvRow> = Vec::new();
+        for result in reader.records() {
+            let record: CsvRow = result?.deserialize(Some(&headers))?;
+            raw_data.push(record);
+        }
+        
+        eprintln!("Loaded {} rows from CSV", raw_data.len());
+        let row_count = raw_data.len();
+        
+        // Parse datetime and sort data
+        let scale = self.config.price_scale;
+        let day_reset = self.config.day_reset_time;
+        let mut parsed_data: Vec<OhlcBar> = raw_data
+            .into_iter()
+            .filter_map(|row| {
+                // Parse the date column which contains datetime
+                let datetime = Self::parse_datetime(&row.date)?;
+                Some(OhlcBar {
+                    datetime,
+                    date: Self::trading_day(datetime, day_reset),
+                    time: datetime.time(),
+                    open: row.open * scale,
+                    high: row.high * scale,
+                    low: row.low * scale,
+                    close: row.close * scale,
+                    volume: row.volume,
+                    candle_type: None,
+                    candle_val: None,
+                    candle_high: None,
+                    candle_low: None,
+                    candle_body_ratio: None,
+                    wick_ratio: None,
+                    source_count: 1,
+                    synthetic: false,
+                    session_high: row.high * scale,
+                    session_low: row.low * scale,
+                    atr: None,
+                    signal: 0,
+                })
+            })
+            .collect();
+        self.rejected_rows = row_count - parsed_data.len();
+        Self::apply_adjustments(&mut parsed_data, &self.adjustments);
+        
+        // Sort by datetime
+        parsed_data.sort_by_key(|bar| bar.datetime);
+        self.check_duplicate_rows(&mut parsed_data)?;
+        
+        let target_interval = self.bar_interval();
+        if let Some(source_interval) = Self::detect_frequency(&parsed_data)
+            && let Some(warning) = Self::frequency_warning(source_interval, target_interval)
+        {
+            eprintln!("Warning: {}", warning);
+        }
+        
+        // Create OHLCV bars at the configured timeframe
+        let bars = if self.config.skip_aggregation {
+            parsed_data
+        } else {
+            Self::create_bars(parsed_data, self.config.timeframe_minutes, self.config.volume_agg)
+        };
+        if self.config.fill_gaps {
+            self.load_bars(self.fill_gaps(bars));
+        } else {
+            self.load_bars(bars);
+        }
+        Ok(())
+    }
+
+    // Combines the trades of separately run strategies (e.g. one per symbol) into a single
+    // portfolio whose metrics and equity curves span all of them
+    pub fn merge_results(runs: &[&NiftyStrategy]) -> NiftyStrategy {
+        let config = runs.first().map(|run| run.config.clone()).unwrap_or_default();
+        let mut merged = NiftyStrategy::with_config(config);
+        merged.trades = runs.iter().flat_map(|run| run.trades.iter().cloned()).collect();
+        merged.trades.sort_by_key(|trade| (trade.date, trade.entry_time));
+        merged.stage = PipelineStage::TradesIdentified;
+        merged
+    }
+
+    // Re-runs the signal and trade stages on the already aggregated bars for each candidate
+    // signal time, as a sensitivity check on the opening-range choice
+    pub fn sweep_signal_time(&self, times: &[NaiveTime]) -> Result<BTreeMap<NaiveTime, PerformanceMetrics>> {
+        let mut results = BTreeMap::new();
+        for &signal_time in times {
+            let config = StrategyConfig { signal_time, ..self.config.clone() };
+            let run = self.run_on_bars(config, self.data.clone())?;
+            results.insert(signal_time, run.calculate_performance_metrics());
+        }
+        Ok(results)
+    }
+
+    // Signal and trade stages for `config` over a subset of the aggregated bars
+    fn run_on_bars(&self, config: StrategyConfig, bars: Vec<OhlcBar>) -> Result<NiftyStrategy> {
+        let mut run = NiftyStrategy::with_config(config);
+        run.instruments = self.instruments.clone();
+        run.has_volume = self.has_volume;
+        run.load_bars(bars);
+        run.identify_signal_candles()?;
+        run.generate_trading_signals()?;
+        run.identify_trades()?;
+        Ok(run)
+    }
+
+    // Rolls an in-sample window of `window_days` trading days forward by `step_days`. Each
+    // window picks the grid entry with the best in-sample PnL and trades it over the next
+    // `step_days` days; the out-of-sample trades are stitched into one cumulative PnL curve.
+    pub fn walk_forward(&self, window_days: usize, step_days: usize, param_grid: &[StrategyConfig]) -> Result<WalkForwardResult> {
+        self.require_stage(PipelineStage::Loaded, "walk_forward")?;
+        if window_days == 0 || step_days == 0 || param_grid.is_empty() {
+            anyhow::bail!("walk_forward needs non-zero window and step sizes and at least one parameter set");
+        }
+        let dates: Vec<NaiveDate> = self.data.iter().map(|bar| bar.date).collect::<BTreeSet<_>>().into_iter().collect();
+        let bars_between = |from: NaiveDate, to: NaiveDate| -> Vec<OhlcBar> {
+            self.data.iter().filter(|bar| bar.date >= from && bar.date <= to).cloned().collect()
+        };
+        
+        let mut result = WalkForwardResult { windows: Vec::new(), equity_curve: vec![0.0] };
+        let mut start = 0;
+        while start + window_days < dates.len() {
+            let in_sample = (dates[start], dates[start + window_days - 1]);
+            let oos_end = (start + window_days + step_days).min(dates.len()) - 1;
+            let out_of_sample = (dates[start + window_days], dates[oos_end]);
+            
+            let in_sample_bars = bars_between(in_sample.0, in_sample.1);
+            let mut best: Option<(usize, f64)> = None;
+            for (idx, config) in param_grid.iter().enumerate() {
+                let pnl = self.run_on_bars(config.clone(), in_sample_bars.clone())?.calculate_performance_metrics().total_pnl;
+                if best.is_none_or(|(_, best_pnl)| pnl > best_pnl) {
+                    best = Some((idx, pnl));
+                }
+            }
+            let (params, _) = best.expect("param_grid is non-empty");
+            
+            let run = self.run_on_bars(param_grid[params].clone(), bars_between(out_of_sample.0, out_of_sample.1))?;
+            let mut equity = *result.equity_curve.last().unwrap();
+            for trade in run.trades.iter().filter(|t| !t.is_open) {
+                equity += trade.net_pnl;
+                result.equity_curve.push(equity);
+            }
+            result.windows.push(WalkForwardWindow {
+                in_sample,
+                out_of_sample,
+                params,
+                metrics: run.calculate_performance_metrics(),
+            });
+            start += step_days;
+        }
+        Ok(result)
+    }
+
+    // Runs the full pipeline and writes the trades. With compute_metrics disabled this stops
+    // after saving, for callers that only need the trades file.
+    pub fn run(&mut self, csv_path: &str, output_path: &str) -> Result<Option<PerformanceMetrics>> {
+        self.load_and_prepare_data(csv_path)?;
+        self.identify_signal_candles()?;
+        self.generate_trading_signals()?;
+        self.identify_trades()?;
+        self.save_results(output_path)?;
+        
+        if !self.config.compute_metrics {
+            return Ok(None);
+        }
+        Ok(Some(self.calculate_performance_metrics()))
+    }
+
+    // Replaces the bar data, e.g. with bars built outside the CSV loader, and resets
+    // the pipeline so the signal stages run again from the start
+    pub fn load_bars(&mut self, bars: Vec<OhlcBar>) {
+        self.data = bars;
+        Self::annotate_session_extremes(&mut self.data);
+        Self::annotate_atr(&mut self.data, self.config.atr_period);
+        self.trades.clear();
+        self.stage = PipelineStage::Loaded;
+    }
+
+    // Running session high/low over bars sorted by datetime, restarting each date
+    fn annotate_session_extremes(bars: &mut [OhlcBar]) {
+        for day_bars in bars.chunk_by_mut(|a, b| a.date == b.date) {
+            let mut high = f64::NEG_INFINITY;
+            let mut low = f64::INFINITY;
+            for bar in day_bars {
+                high = high.max(bar.high);
+                low = low.min(bar.low);
+                bar.session_high = high;
+                bar.session_low = low;
+            }
+        }
+    }
+
+    // Wilder ATR over bars sorted by datetime: seeded with the mean true range of the first
+    // `period` bars, then smoothed. True range spans the prior close, overnight included.
+    fn annotate_atr(bars: &mut [OhlcBar], period: usize) {
+        let mut prior_close: Option<f64> = None;
+        let mut atr: Option<f64> = None;
+        let mut seed_sum = 0.0;
+        for (i, bar) in bars.iter_mut().enumerate() {
+            let true_range = match prior_close {
+                Some(close) => bar.high.max(close) - bar.low.min(close),
+                None => bar.high - bar.low,
+            };
+            prior_close = Some(bar.close);
+            atr = match atr {
+                Some(previous) => Some((previous * (period - 1) as f64 + true_range) / period as f64),
+                None if period > 0 => {
+                    seed_sum += true_range;
+                    (i + 1 == period).then(|| seed_sum / period as f64)
+                }
+                None => None,
+            };
+            bar.atr = atr;
+        }
+    }
+
+    fn require_stage(&self, required: PipelineStage, step: &str) -> Result<()> {
+        if self.stage < required {
+            anyhow::bail!(
+                "{} requires the {:?} stage but the pipeline is at {:?}; run load_and_prepare_data, \
+                 identify_signal_candles, generate_trading_signals and identify_trades in order",
+                step, required, self.stage
+            );
+        }
+        Ok(())
+    }
+
+    // Writes the effective configuration so the run can be reproduced
+    pub fn save_config(&self, config_path: &str) -> Result<()> {
+        self.config.save(config_path)
+    }
+
+    pub fn load_symbols(&mut self, symbols_path: &str) -> Result<()> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(csv::Trim::All)
+            .from_path(symbols_path)?;
+        
+        for result in reader.deserialize() {
+            let instrument: Instrument = result?;
+            self.instruments.insert(instrument.symbol.clone(), instrument);
+        }
+        
+        eprintln!("Loaded {} instruments", self.instruments.len());
+        Ok(())
+    }
+
+    // Reads a `date,factor` corporate-actions file; load it before the price data
+    pub fn load_adjustments(&mut self, adjustments_path: &str) -> Result<()> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(csv::Trim::All)
+            .from_path(adjustments_path)?;
+        
+        self.adjustments.clear();
+        for result in reader.deserialize() {
+            let action: CorporateAction = result?;
+            self.adjustments.push(action);
+        }
+        
+        eprintln!("Loaded {} corporate actions", self.adjustments.len());
+        Ok(())
+    }
+
+    // Back-adjusts OHLC by the cumulative factor of every action after each bar's date, so
+    // prices are continuous across splits and dividends and no false gap arms a signal
+    fn apply_adjustments(bars: &mut [OhlcBar], actions: &[CorporateAction]) {
+        if actions.is_empty() {
+            return;
+        }
+        for bar in bars.iter_mut() {
+            let factor: f64 = actions.iter()
+                .filter(|action| bar.date < action.date)
+                .map(|action| action.factor)
+                .product();
+            bar.open *= factor;
+            bar.high *= factor;
+            bar.low *= factor;
+            bar.close *= factor;
+        }
+    }
+
+    fn parse_datetime(datetime_str: &str) -> Option<NaiveDateTime> {
+        // Try common datetime formats
+        let formats = [
+            "%Y-%m-%d %H:%M:%S",
+            "%d-%m-%Y %H:%M:%S", 
+            "%Y/%m/%d %H:%M:%S",
+            "%d/%m/%Y %H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%d-%m-%Y %H:%M",
+        ];
+        
+        for format in &formats {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(datetime_str, format) {
+                return Some(dt);
+            }
+        }
+        
+        // Unix epoch in seconds or milliseconds (UTC). The ranges cover 1973-2286 and keep
+        // short integers such as a bare year from being read as an epoch.
+        let epoch: i64 = datetime_str.parse().ok()?;
+        let millis = match epoch {
+            100_000_000..=9_999_999_999 => epoch * 1000,
+            100_000_000_000..=9_999_999_999_999 => epoch,
+            _ => return None,
+        };
+        chrono::DateTime::from_timestamp_millis(millis).map(|dt| dt.naive_utc())
+    }
+
+    // Date range, bar density, price range and gaps of the loaded bars; None before loading
+    pub fn data_health_report(&self) -> Option<DataHealthReport> {
+        let first = self.data.first()?;
+        let last = self.data.last()?;
+        let interval = self.bar_interval();
+        let trading_days = self.data.chunk_by(|a, b| a.date == b.date).count();
+        // Gaps are counted on the source bars, before any synthetic fill
+        let real_bars: Vec<&OhlcBar> = self.data.iter().filter(|bar| !bar.synthetic).collect();
+        let gaps = real_bars.windows(2)
+            .filter(|pair| pair[0].date == pair[1].date && pair[1].datetime - pair[0].datetime > interval)
+            .count();
+        
+        Some(DataHealthReport {
+            first_date: first.date,
+            last_date: last.date,
+            trading_days,
+            avg_bars_per_day: self.data.len() as f64 / trading_days as f64,
+            min_price: self.data.iter().map(|bar| bar.low).fold(f64::INFINITY, f64::min),
+            max_price: self.data.iter().map(|bar| bar.high).fold(f64::NEG_INFINITY, f64::max),
+            rejected_rows: self.rejected_rows,
+            gaps,
+        })
+    }
+
+    // Counts rows repeating an earlier (datetime, open, high, low, close), dropping them with
+    // dedupe_rows set. Rows only need to share a timestamp, not be adjacent.
+    fn check_duplicate_rows(&self, rows: &mut Vec<OhlcBar>) -> Result<()> {
+        let mut seen: BTreeSet<(NaiveDateTime, [u64; 4])> = BTreeSet::new();
+        let mut duplicate = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let key = [row.open, row.high, row.low, row.close].map(f64::to_bits);
+            duplicate.push(!seen.insert((row.datetime, key)));
+        }
+        let duplicates = duplicate.iter().filter(|d| **d).count();
+        
+        if let Some(limit) = self.config.max_duplicate_rows
+            && duplicates > limit
+        {
+            let message = format!("{} duplicate rows found, above the limit of {}", duplicates, limit);
+            match self.config.duplicate_rows_policy {
+                DataIssuePolicy::Warn => eprintln!("Warning: {}", message),
+                DataIssuePolicy::Error => anyhow::bail!(message),
+            }
+        }
+        if self.config.dedupe_rows && duplicates > 0 {
+            let mut flags = duplicate.into_iter();
+            rows.retain(|_| !flags.next().unwrap_or(false));
+            eprintln!("Dropped {} duplicate rows", duplicates);
+        }
+        Ok(())
+    }
+
+    // Modal gap between consecutive timestamps within a session; ties go to the shorter gap
+    fn detect_frequency(bars: &[OhlcBar]) -> Option<chrono::Duration> {
+        let mut gap_counts: HashMap<i64, usize> = HashMap::new();
+        for pair in bars.windows(2) {
+            let gap = (pair[1].datetime - pair[0].datetime).num_seconds();
+            if pair[0].date == pair[1].date && gap > 0 {
+                *gap_counts.entry(gap).or_insert(0) += 1;
+            }
+        }
+        
+        gap_counts.into_iter()
+            .max_by(|(gap_a, count_a), (gap_b, count_b)| count_a.cmp(count_b).then(gap_b.cmp(gap_a)))
+            .map(|(gap, _)| chrono::Duration::seconds(gap))
+    }
+
+    // Aggregating to an interval finer than the source leaves most buckets empty
+    fn frequency_warning(source_interval: chrono::Duration, target_interval: chrono::Duration) -> Option<String> {
+        (target_interval < source_interval).then(|| format!(
+            "target bar interval of {}s is smaller than the source data frequency of {}s; bars will be sparse",
+            target_interval.num_seconds(), source_interval.num_seconds()
+        ))
+    }
+
+    // Inserts a flat, zero-volume bar at the prior close for each missing bar slot between
+    // consecutive bars of the same session. Overnight gaps, session breaks and slots outside
+    // session hours are left alone.
+    pub fn fill_gaps(&self, bars: Vec<OhlcBar>) -> Vec<OhlcBar> {
+        let mut filled: Vec<OhlcBar> = Vec::with_capacity(bars.len());
+        for bar in bars {
+            if let Some(prior) = filled.last().cloned()
+                && prior.date == bar.date
+            {
+                for datetime in self.missing_slots(prior.datetime, bar.datetime) {
+                    filled.push(OhlcBar {
+                        datetime,
+                        time: datetime.time(),
+                        open: prior.close,
+                        high: prior.close,
+                        low: prior.close,
+                        volume: 0.0,
+                        source_count: 0,
+                        synthetic: true,
+                        ..prior.clone()
+                    });
+                }
+            }
+            filled.push(bar);
+        }
+        filled
+    }
+
+    // Gaps fill_gaps would fill in the source bars, as [first missing slot, next bar) ranges
+    pub fn detect_gaps(&self) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        let real_bars: Vec<&OhlcBar> = self.data.iter().filter(|bar| !bar.synthetic).collect();
+        real_bars.windows(2)
+            .filter(|pair| pair[0].date == pair[1].date)
+            .filter_map(|pair| {
+                let first_missing = *self.missing_slots(pair[0].datetime, pair[1].datetime).first()?;
+                Some((first_missing, pair[1].datetime))
+            })
+            .collect()
+    }
+
+    // Bar start times strictly between two bars that fall within trading hours
+    fn missing_slots(&self, prior: NaiveDateTime, next: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let interval = self.bar_interval();
+        let mut slots = Vec::new();
+        let mut datetime = prior + interval;
+        while datetime < next {
+            if self.in_trading_hours(datetime.time()) {
+                slots.push(datetime);
+            }
+            datetime += interval;
+        }
+        slots
+    }
+
+    // Inside [session_start, session_end), wrapping past midnight for evening sessions, and
+    // outside every session break
+    fn in_trading_hours(&self, time: NaiveTime) -> bool {
+        let (start, end) = (self.config.session_start, self.config.session_end);
+        let in_session = if start <= end { time >= start && time < end } else { time >= start || time < end };
+        in_session && !self.config.session_breaks.iter().any(|(from, to)| time >= *from && time < *to)
+    }
+
+    // Trading day a timestamp belongs to when days roll over at `day_reset` rather than midnight
+    fn trading_day(datetime: NaiveDateTime, day_reset: NaiveTime) -> NaiveDate {
+        if day_reset != NaiveTime::MIN && datetime.time() >= day_reset {
+            datetime.date() + chrono::Duration::days(1)
+        } else {
+            datetime.date()
+        }
+    }
+
+    // Width of the bars produced by the loader
+    fn bar_interval(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.config.timeframe_minutes as i64)
+    }
+
+    pub fn create_bars(data: Vec<OhlcBar>, minutes: u32, volume_agg: VolumeAggregation) -> Vec<OhlcBar> {
+        let mut bars = Vec::new();
+        let mut current_group: Vec<OhlcBar> = Vec::new();
+        let mut current_start: Option<NaiveDateTime> = None;
+        
+        for bar in data {
+            // Calculate the bucket boundary
+            let bar_start = Self::round_to_timeframe(bar.datetime, minutes);
+            
+            if current_start.is_none() {
+                current_start = Some(bar_start);
+            }
+            
+            if current_start == Some(bar_start) {
+                current_group.push(bar);
+            } else {
+                // Process current group and start new group
+                if !current_group.is_empty() {
+                    bars.push(Self::aggregate_bars(&current_group, current_start.unwrap(), volume_agg));
+                }
+                current_group.clear();
+                current_group.push(bar);
+                current_start = Some(bar_start);
+            }
+        }
+        
+        // Process last group
+        if !current_group.is_empty() {
+            bars.push(Self::aggregate_bars(&current_group, current_start.unwrap(), volume_agg));
+        }
+        
+        // Overlapping inputs can yield the same bucket twice; keep the first occurrence
+        bars.sort_by_key(|bar| bar.datetime);
+        bars.dedup_by_key(|bar| bar.datetime);
+        
+        bars
+    }
+
+    // Start of the `minutes`-wide bucket holding `datetime`; `minutes` divides 60
+    fn round_to_timeframe(datetime: NaiveDateTime, minutes: u32) -> NaiveDateTime {
+        let minute = datetime.minute();
+        let rounded_minute = (minute / minutes) * minutes;
+        datetime.with_minute(rounded_minute).unwrap().with_second(0).unwrap()
+    }
+
+    // Daily or weekly bars stamped with the start of their first session. Holidays and
+    // partial weeks at either end of the data simply yield bars with fewer sessions.
+    pub fn create_period_bars(mut data: Vec<OhlcBar>, period: BarPeriod) -> Vec<OhlcBar> {
+        data.sort_by_key(|bar| bar.datetime);
+        let same_period = |a: &OhlcBar, b: &OhlcBar| match period {
+            BarPeriod::Daily => a.date == b.date,
+            BarPeriod::Weekly => a.date.iso_week() == b.date.iso_week(),
+        };
+        data.chunk_by(same_period)
+            .map(|group| Self::aggregate_bars(group, group[0].datetime, VolumeAggregation::Sum))
+            .collect()
+    }
+
+    // Range bars: constituents accumulate until the bar's high-low span reaches `range_points`,
+    // and the bar is stamped with its last constituent's time. Bars never span sessions, so
+    // each day's final bar may fall short of the full range.
+    pub fn create_range_bars(mut data: Vec<OhlcBar>, range_points: f64, volume_agg: VolumeAggregation) -> Vec<OhlcBar> {
+        data.sort_by_key(|bar| bar.datetime);
+        let mut bars = Vec::new();
+        for day_bars in data.chunk_by(|a, b| a.date == b.date) {
+            let mut start = 0;
+            let mut high = f64::NEG_INFINITY;
+            let mut low = f64::INFINITY;
+            for (i, bar) in day_bars.iter().enumerate() {
+                high = high.max(bar.high);
+                low = low.min(bar.low);
+                if high - low >= range_points || i == day_bars.len() - 1 {
+                    let group = &day_bars[start..=i];
+                    bars.push(Self::aggregate_bars(group, bar.datetime, volume_agg));
+                    start = i + 1;
+                    high = f64::NEG_INFINITY;
+                    low = f64::INFINITY;
+                }
+            }
+        }
+        bars
+    }
+
+    fn aggregate_bars(bars: &[OhlcBar], start: NaiveDateTime, volume_agg: VolumeAggregation) -> OhlcBar {
+        let first = &bars[0];
+        let last = &bars[bars.len() - 1];
+        
+        let open = first.open;
+        let close = last.close;
+        let high = bars.iter().map(|b| b.high).fold(f64::NEG_INFINITY, f64::max);
+        let low = bars.iter().map(|b| b.low).fold(f64::INFINITY, f64::min);
+        let volume = match volume_agg {
+            VolumeAggregation::Sum => bars.iter().map(|b| b.volume).sum(),
+            VolumeAggregation::Last => last.volume,
+        };
+        
+        OhlcBar {
+            datetime: start,
+            date: first.date,
+            time: start.time(),
+            open,
+            high,
+            low,
+            close,
+            volume,
+            candle_type: None,
+            candle_val: None,
+            candle_high: None,
+            candle_low: None,
+            candle_body_ratio: None,
+            wick_ratio: None,
+            source_count: bars.iter().map(|b| b.source_count).sum(),
+            synthetic: false,
+            session_high: high,
+            session_low: low,
+            atr: None,
+            signal: 0,
+        }
+    }
+
+    pub fn identify_signal_candles(&mut self) -> Result<()> {
+        self.require_stage(PipelineStage::Loaded, "identify_signal_candles")?;
+        let step_start = Instant::now();
+        
+        let target_time = self.config.signal_time;
+        let mut signal_days = 0;
+        let mut body_ratio_drops = 0;
+        
+        // Bars are sorted by datetime, so each date is a contiguous run: find the
+        // signal candle and label the day's bars in the same pass. If several bars
+        // share the signal time, the first one in datetime order wins. Every bar is
+        // rewritten, so labels from an earlier run never leak into an unarmed day.
+        for day_bars in self.data.chunk_by_mut(|a, b| a.date == b.date) {
+            let signal = day_bars.iter()
+                .filter(|bar| bar.time == target_time)
+                .min_by_key(|bar| bar.datetime)
+                .and_then(|bar| {
+                    Self::classify_signal_candle(bar, self.config.doji_as, self.config.doji_body_threshold)
+                        .map(|classified| (classified, bar.high, bar.low, (bar.close - bar.open).abs()))
+                });
+            
+            let mut body_ratio = None;
+            let mut label = None;
+            if let Some(((candle_type, candle_val), candle_high, candle_low, body)) = signal {
+                let range = candle_high - candle_low;
+                let ratio = if range > 0.0 { body / range } else { 0.0 };
+                body_ratio = Some(ratio);
+                // Wick-dominated signal candles are scored but do not arm a trade
+                if self.config.min_body_ratio.is_some_and(|min| ratio < min) {
+                    body_ratio_drops += 1;
+                } else {
+                    signal_days += 1;
+                    label = Some((candle_type, candle_val, candle_high, candle_low));
+                }
+            }
+            
+            for bar in day_bars.iter_mut() {
+                bar.candle_body_ratio = body_ratio;
+                bar.candle_type = label.as_ref().map(|(candle_type, ..)| candle_type.clone());
+                bar.candle_val = label.as_ref().map(|(_, candle_val, ..)| *candle_val);
+                bar.candle_high = label.as_ref().map(|(_, _, candle_high, _)| *candle_high);
+                bar.candle_low = label.as_ref().map(|(.., candle_low)| *candle_low);
+                bar.signal = 0;
+            }
+        }
+        
+        eprintln!("Signal identification completed in {:.2} seconds", step_start.elapsed().as_secs_f64());
+        eprintln!("Found {} signal days", signal_days);
+        self.filter_drops.insert("min_body_ratio", body_ratio_drops);
+        self.stage = PipelineStage::CandlesIdentified;
+        Ok(())
+    }
+
+    // Close of the last bar on the most recent trading day before `date` that exists in the
+    // data, so weekends and holidays are skipped rather than assumed to be calendar-adjacent
+    pub fn prior_trading_day_close(&self, date: NaiveDate) -> Option<f64> {
+        let idx = self.data.partition_point(|bar| bar.date < date);
+        idx.checked_sub(1).map(|i| self.data[i].close)
+    }
+
+    fn classify_signal_candle(bar: &OhlcBar, doji_as: DojiPolicy, doji_body_threshold: f64) -> Option<(String, f64)> {
+        // Neutral candles keep their close as the level but never match a breakout direction
+        if (bar.close - bar.open).abs() < doji_body_threshold {
+            return Some(("doji".to_string(), bar.close));
+        }
+        
+        let bullish = if bar.close == bar.open {
+            match doji_as {
+                DojiPolicy::Bullish => true,
+                DojiPolicy::Bearish => false,
+                DojiPolicy::Skip => return None,
+            }
+        } else {
+            bar.close > bar.open
+        };
+        
+        if bullish {
+            Some(("bullish".to_string(), bar.high))
+        } else {
+            Some(("bearish".to_string(), bar.low))
+        }
+    }
+
+    pub fn generate_trading_signals(&mut self) -> Result<()> {
+        self.require_stage(PipelineStage::CandlesIdentified, "generate_trading_signals")?;
+        let step_start = Instant::now();
+        
+        match self.config.signal_source {
+            SignalSource::SignalCandle => {
+                let fade = self.config.mixed_day_policy == MixedDayPolicy::Fade;
+                // Signal candle type of the previous trading day, for require_candle_flip
+                let mut prior_type: Option<String> = None;
+                for day_bars in self.data.chunk_by_mut(|a, b| a.date == b.date) {
+                    let day_type = day_bars[0].candle_type.clone();
+                    let flipped = matches!(
+                        (prior_type.as_deref(), day_type.as_deref()),
+                        (Some("bearish"), Some("bullish")) | (Some("bullish"), Some("bearish"))
+                    );
+                    prior_type = day_type;
+                    if self.config.require_candle_flip && !flipped {
+                        continue;
+                    }
+                    
+                    for bar in day_bars.iter_mut() {
+                        // identify_signal_candles sets both or neither; anything else is a pipeline bug
+                        if bar.candle_type.is_some() != bar.candle_val.is_some() {
+                            anyhow::bail!(
+                                "bar at {} has a half-populated signal candle (type {:?}, level {:?})",
+                                bar.datetime, bar.candle_type, bar.candle_val
+                            );
+                        }
+                        if let (Some(candle_type), Some(candle_val)) = (&bar.candle_type, bar.candle_val) {
+                            let candle_high = bar.candle_high.unwrap_or(f64::INFINITY);
+                            let candle_low = bar.candle_low.unwrap_or(f64::NEG_INFINITY);
+                            bar.signal = match candle_type.as_str() {
+                                "bearish" if bar.close < candle_val => -1,
+                                "bullish" if bar.close > candle_val => 1,
+                                // Mixed day: the break went against the candle, so fade it
+                                "bullish" if fade && bar.close < candle_low => 1,
+                                "bearish" if fade && bar.close > candle_high => -1,
+                                _ => 0,
+                            };
+                        }
+                    }
+                }
+            }
+            SignalSource::MomentumBreakout => {
+                // Compare each bar against the previous bar of the same session
+                for i in 0..self.data.len() {
+                    let prior = (i > 0 && self.data[i - 1].date == self.data[i].date)
+                        .then(|| (self.data[i - 1].high, self.data[i - 1].low));
+                    let bar = &mut self.data[i];
+                    bar.signal = match prior {
+                        Some((prior_high, _)) if bar.close > prior_high => 1,
+                        Some((_, prior_low)) if bar.close < prior_low => -1,
+                        _ => 0,
+                    };
+                }
+            }
+            SignalSource::WickRejection => {
+                let min_ratio = self.config.min_wick_ratio;
+                let target_time = self.config.signal_time;
+                for day_bars in self.data.chunk_by_mut(|a, b| a.date == b.date) {
+                    // Only the signal candle is judged; like its type, the verdict holds all session
+                    let wicks = day_bars.iter()
+                        .filter(|bar| bar.time == target_time)
+                        .min_by_key(|bar| bar.datetime)
+                        .filter(|bar| bar.high > bar.low)
+                        .map(|bar| {
+                            let range = bar.high - bar.low;
+                            ((bar.open.min(bar.close) - bar.low) / range, (bar.high - bar.open.max(bar.close)) / range)
+                        });
+                    let signal = match wicks {
+                        Some((lower, upper)) if lower > upper && lower > min_ratio => 1,
+                        Some((lower, upper)) if upper > lower && upper > min_ratio => -1,
+                        _ => 0,
+                    };
+                    for bar in day_bars.iter_mut() {
+                        bar.wick_ratio = wicks.map(|(lower, upper)| lower.max(upper));
+                        bar.signal = signal;
+                    }
+                }
+            }
+        }
+        
+        eprintln!("Signal generation completed in {:.2} seconds", step_start.elapsed().as_secs_f64());
+        self.stage = PipelineStage::SignalsGenerated;
+        Ok(())
+    }
+
+    pub fn identify_trades(&mut self) -> Result<()> {
+        self.require_stage(PipelineStage::SignalsGenerated, "identify_trades")?;
+        let step_start = Instant::now();
+        self.trades.clear();
+        
+        // Entries start once the signal candle has completed
+        let start_time = self.config.signal_time + self.bar_interval();
+        let end_time = NaiveTime::from_hms_opt(15, 15, 0).unwrap();
+        let exit_time = NaiveTime::from_hms_opt(15, 15, 0).unwrap();
+        
+        // Boundary days are dropped once the full set of trading dates is known
+        let dates: BTreeSet<NaiveDate> = self.data.iter().map(|bar| bar.date).collect();
+        let tradable: BTreeSet<NaiveDate> = dates.iter()
+            .skip(self.config.skip_first_n_days)
+            .take(dates.len().saturating_sub(self.config.skip_first_n_days + self.config.skip_last_n_days))
+            .copied()
+            .collect();
+        
+        // Group data by date; days are walked in order so the drawdown halt sees a running curve
+        let mut date_groups: BTreeMap<NaiveDate, Vec<&OhlcBar>> = BTreeMap::new();
+        for bar in &self.data {
+            if bar.time >= start_time && bar.time <= end_time && tradable.contains(&bar.date) {
+                date_groups.entry(bar.date).or_default().push(bar);
+            }
+        }
+        
+        let last_date = self.data.iter().map(|bar| bar.date).max();
+        let mut trade_drops: BTreeMap<&'static str, usize> =
+            [("max_volume_participation", 0), ("max_risk_per_trade", 0)].into_iter().collect();
+        let mut equity = 0.0_f64;
+        let mut peak = 0.0_f64;
+        let mut halted = false;
+        
+        // Process each trading day
+        for (date, mut day_bars) in date_groups {
+            // Signals must be acted on in the order they occurred, even if bars were supplied unsorted
+            day_bars.sort_by_key(|bar| bar.datetime);
+            
+            // Forced exit bar at 15:15 or last available
+            let forced_exit_idx = day_bars.iter()
+                .position(|bar| bar.time == exit_time)
+                .unwrap_or(day_bars.len() - 1);
+            
+            // Take signals in order, re-entering after each exit up to the daily limit
+            let mut search_from = 0;
+            let mut trades_today = 0;
+            while trades_today < self.config.max_trades_per_day {
+                let Some(entry) = self.next_entry(&day_bars, search_from, forced_exit_idx, trades_today > 0) else {
+                    break;
+                };
+                let (exit_idx, raw_exit_price, exit_reason) =
+                    self.simulate_exit(&day_bars, entry, forced_exit_idx);
+                
+                // The data ended before the forced exit time with this position still held
+                let left_open = self.config.allow_open_trade_at_end
+                    && Some(date) == last_date
+                    && exit_reason == ExitReason::EndOfDay
+                    && day_bars[forced_exit_idx].time != exit_time;
+                
+                match self.build_trade(date, &day_bars, entry, exit_idx, raw_exit_price, exit_reason) {
+                    Ok(mut trade) => {
+                        if left_open {
+                            trade.is_open = true;
+                            trade.exit_time = None;
+                            trade.exit_price = None;
+                            trade.exit_reason = ExitReason::Open;
+                            trade.exit_bar_ohlcv = None;
+                            trade.gross_pnl = 0.0;
+                            trade.net_pnl = 0.0;
+                        }
+                        trade.tag = self.tagger.as_ref().and_then(|tagger| tagger(&trade, day_bars[entry.idx]));
+                        equity += trade.net_pnl;
+                        if halted {
+                            *trade_drops.entry("max_drawdown_halt").or_insert(0) += 1;
+                        } else {
+                            self.trades.push(trade);
+                        }
+                        trades_today += 1;
+                        
+                        peak = peak.max(equity);
+                        let drawdown = peak - equity;
+                        if self.config.max_drawdown_halt.is_some_and(|limit| drawdown > limit) {
+                            halted = true;
+                        } else if halted && self.config.drawdown_resume.is_some_and(|resume| drawdown <= resume) {
+                            halted = false;
+                        }
+                    }
+                    Err(filter) => *trade_drops.entry(filter).or_insert(0) += 1,
+                }
+                if exit_idx >= forced_exit_idx {
+                    break;
+                }
+                search_from = exit_idx + 1;
+            }
+        }
+        
+        self.sort_trades();
+        self.validate_trade_order()?;
+        
+        eprintln!("Trade identification completed in {:.2} seconds", step_start.elapsed().as_secs_f64());
+        eprintln!("Identified {} trades", self.trades.len());
+        self.filter_drops.extend(trade_drops);
+        if let Some(diagnostic) = self.filter_diagnostic() {
+            eprintln!("{}", diagnostic);
+        }
+        self.stage = PipelineStage::TradesIdentified;
+        Ok(())
+    }
+
+    // First signal bar at or after `from`. After an exit with require_rearm set, the close
+    // must first retreat back inside the level before a fresh breakout counts. In retest
+    // mode the fill waits for a bar before the forced exit to trade back to the level.
+    fn next_entry(&self, day_bars: &[&OhlcBar], from: usize, forced_exit_idx: usize, after_exit: bool) -> Option<Entry> {
+        let mut start = from;
+        if after_exit && self.config.require_rearm {
+            start += day_bars[from..].iter().position(|bar| bar.signal == 0)?;
+        }
+        let breakout_idx = day_bars[start..].iter()
+            .position(|bar| bar.signal != 0)
+            .map(|offset| start + offset)?;
+        let breakout = day_bars[breakout_idx];
+        
+        match self.config.entry_mode {
+            EntryMode::Breakout => Some(Entry { idx: breakout_idx, signal: breakout.signal, price: breakout.close }),
+            EntryMode::Retest => {
+                let level = breakout.candle_val?;
+                (breakout_idx + 1..forced_exit_idx).find_map(|idx| {
+                    let bar = day_bars[idx];
+                    // A bar opening beyond the level fills at its open
+                    let price = match breakout.signal {
+                        1 if bar.low <= level => bar.open.min(level),
+                        -1 if bar.high >= level => bar.open.max(level),
+                        _ => return None,
+                    };
+                    Some(Entry { idx, signal: breakout.signal, price })
+                })
+            }
+        }
+    }
+
+    // Prices and costs a round trip; Err names the filter that rejected the setup
+    fn build_trade(
+        &self,
+        date: NaiveDate,
+        day_bars: &[&OhlcBar],
+        entry: Entry,
+        exit_idx: usize,
+        raw_exit_price: f64,
+        exit_reason: ExitReason,
+    ) -> std::result::Result<Trade, &'static str> {
+        let entry_bar = day_bars[entry.idx];
+        let exit_bar = day_bars[exit_idx];
+        
+        let instrument = self.instruments.get(&self.config.symbol);
+        // Buys fill at the ask and sells at the bid, half a spread either side of the bar price,
+        // and slippage moves each fill further against the trade
+        let half_spread = self.config.spread_points / 2.0;
+        let slippage = self.config.slippage;
+        let direction = entry.signal as f64;
+        let entry_price = Self::round_to_tick(entry.price, instrument)
+            + direction * (half_spread + slippage.points(entry.price));
+        let exit_price = Self::round_to_tick(raw_exit_price, instrument)
+            - direction * (half_spread + slippage.points(raw_exit_price));
+        
+        // Orders go out in whole lots, so every sizing step rounds down to the lot size
+        if Self::round_to_lot(self.config.quantity, instrument) <= 0.0 {
+            return Err("lot_size");
+        }
+        let quantity = match self.config.max_volume_participation {
+            // Each leg can only absorb a fraction of its bar's volume
+            Some(participation) if self.has_volume => self.config.quantity
+                .min(entry_bar.volume * participation)
+                .min(exit_bar.volume * participation),
+            _ => self.config.quantity,
+        };
+        let mut quantity = Self::round_to_lot(quantity, instrument);
+        if quantity <= 0.0 {
+            return Err("max_volume_participation");
+        }
+        let point_value = instrument.map_or(1.0, |i| i.point_value);
+        
+        // Risk from entry to the stop must fit under the per-trade cap
+        if let (Some(max_risk), Some(stop)) = (self.config.max_risk_per_trade, self.stop_price(entry_bar, entry)) {
+            let risk_per_unit = (entry.price - stop).abs() * point_value;
+            if risk_per_unit * quantity > max_risk {
+                match self.config.risk_cap_policy {
+                    RiskCapPolicy::Skip => return Err("max_risk_per_trade"),
+                    RiskCapPolicy::ReduceSize => quantity = Self::round_to_lot(max_risk / risk_per_unit, instrument),
+                }
+                if quantity <= 0.0 {
+                    return Err("max_risk_per_trade");
+                }
+            }
+        }
+        
+        // Calculate PnL
+        let points = if entry.signal == -1 {
+            entry_price - exit_price // Short position
+        } else {
+            exit_price - entry_price // Long position
+        };
+        let gross_pnl = points * quantity * point_value;
+        
+        let transaction_cost = self.transaction_cost(instrument, entry_price, exit_price, quantity);
+        let net_pnl = gross_pnl - transaction_cost;
+        
+        let (mfe, bars_to_peak) = Self::favorable_excursion(
+            &day_bars[entry.idx..=exit_idx], entry_price, exit_price, entry.signal);
+        
+        Ok(Trade {
+            symbol: self.config.symbol.clone(),
+            date,
+            entry_time: entry_bar.datetime,
+            entry_price,
+            exit_time: Some(exit_bar.datetime),
+            exit_price: Some(exit_price),
+            exit_reason,
+            quantity,
+            point_value,
+            signal: entry.signal,
+            gross_pnl,
+            net_pnl,
+            mfe,
+            bars_to_peak,
+            bars_held: exit_idx - entry.idx,
+            is_open: false,
+            tag: None,
+            entry_bar_ohlcv: BarOhlcv::from(entry_bar),
+            exit_bar_ohlcv: Some(BarOhlcv::from(exit_bar)),
+        })
+    }
+
+    // Explains an empty trade list: how many signals fired and what each filter removed
+    fn filter_diagnostic(&self) -> Option<String> {
+        if !self.trades.is_empty() {
+            return None;
+        }
+        
+        let signals = self.data.iter().filter(|bar| bar.signal != 0).count();
+        let mut report = format!("No trades: {} signal bars generated", signals);
+        for (filter, dropped) in &self.filter_drops {
+            report.push_str(&format!(", {} dropped by {}", dropped, filter));
+        }
+        Some(report)
+    }
+
+    // Sorts by date, or by entry timestamp when sort_by_entry_time is set. The sort is stable,
+    // so same-date trades otherwise keep the order they were generated in.
+    pub fn sort_trades(&mut self) {
+        if self.config.sort_by_entry_time {
+            self.trades.sort_by_key(|trade| trade.entry_time);
+        } else {
+            self.trades.sort_by_key(|trade| trade.date);
+        }
+    }
+
+    // Entries within a day must be non-decreasing in time, as trades are generated by
+    // walking each day's signals chronologically
+    fn validate_trade_order(&self) -> Result<()> {
+        for pair in self.trades.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            if prev.date == next.date && next.entry_time < prev.entry_time {
+                anyhow::bail!(
+                    "trades on {} are out of order: entry at {} follows entry at {}",
+                    next.date, next.entry_time, prev.entry_time
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Protective stop level for a position opened on entry_bar, if stops are enabled
+    fn stop_price(&self, entry_bar: &OhlcBar, entry: Entry) -> Option<f64> {
+        let direction = entry.signal as f64;
+        match self.config.stop_basis {
+            StopBasis::Points => self.config.stop_loss_points.map(|points| entry.price - direction * points),
+            // Longs are stopped below the signal candle's low, shorts above its high
+            StopBasis::SignalCandleExtreme => if entry.signal == 1 { entry_bar.candle_low } else { entry_bar.candle_high },
+        }
+    }
+
+    // Walks the bars after entry and returns the exit bar index, the raw (pre-spread) exit
+    // price and why the position closed. Stops and targets fill at their level; a bar through
+    // both resolves by exit_precedence, stop first by default. Without an earlier exit the
+    // position is closed at the open of the forced-exit bar. If that open is already through
+    // the stop or target, both exits fill at the same price and the stop or target wins.
+    fn simulate_exit(&self, day_bars: &[&OhlcBar], entry: Entry, forced_exit_idx: usize) -> (usize, f64, ExitReason) {
+        let entry_bar = day_bars[entry.idx];
+        let mut stop_price = self.stop_price(entry_bar, entry);
+        let direction = entry.signal as f64;
+        let risk = stop_price.map(|stop| (entry.price - stop).abs()).filter(|risk| *risk > 0.0);
+        let atr_target = self.config.atr_target_multiple.zip(entry_bar.atr).map(|(multiple, atr)| multiple * atr);
+        let target_price = match (self.config.take_profit_points, atr_target) {
+            (Some(points), Some(atr_points)) => Some(points.min(atr_points)),
+            (points, atr_points) => points.or(atr_points),
+        }.map(|points| entry.price + direction * points);
+        let activation_delay = chrono::Duration::minutes(self.config.stop_activation_delay_minutes);
+        let dead_trade_after = self.config.dead_trade_minutes.map(chrono::Duration::minutes);
+        let mut progress_checked = false;
+        
+        // Only the bars strictly between the entry and the forced exit are scanned
+        for (idx, bar) in day_bars.iter().enumerate().take(forced_exit_idx).skip(entry.idx + 1) {
+            let held = self.trading_duration(entry_bar.datetime, bar.datetime);
+            // Stops and targets only arm once the trade has been open for the activation delay
+            if held >= activation_delay {
+                let stop_hit = stop_price
+                    .filter(|stop| if entry.signal == 1 { bar.low <= *stop } else { bar.high >= *stop });
+                let target_hit = target_price
+                    .filter(|target| if entry.signal == 1 { bar.high >= *target } else { bar.low <= *target });
+                match (stop_hit, target_hit) {
+                    (Some(_), Some(target)) if self.config.exit_precedence == ExitPrecedence::TargetFirst => {
+                        return (idx, target, ExitReason::TakeProfit);
+                    }
+                    (Some(stop), _) => return (idx, stop, ExitReason::StopLoss),
+                    (None, Some(target)) => return (idx, target, ExitReason::TakeProfit),
+                    (None, None) => {}
+                }
+                // Ratchet the stop once the bar has completed, so it applies from the next bar;
+                // moves inside the activation delay never lock anything in
+                if let (Some(risk), Some(stop)) = (risk, stop_price) {
+                    let best = if entry.signal == 1 { bar.high } else { bar.low };
+                    let run_r = (best - entry.price) * direction / risk;
+                    let locked = self.config.r_based_trailing.iter()
+                        .filter(|(trigger, _)| run_r >= *trigger)
+                        .map(|(_, lock)| entry.price + direction * lock * risk)
+                        .fold(stop, |current, level| if entry.signal == 1 { current.max(level) } else { current.min(level) });
+                    stop_price = Some(locked);
+                }
+            }
+            // Progress is judged once, at the close of the first bar past the deadline
+            if !progress_checked && dead_trade_after.is_some_and(|after| held >= after) {
+                progress_checked = true;
+                let progress = (bar.close - entry.price) * entry.signal as f64;
+                if progress < self.config.min_progress_points {
+                    return (idx, bar.close, ExitReason::NoProgress);
+                }
+            }
+        }
+        
+        let exit_bar = day_bars[forced_exit_idx];
+        if forced_exit_idx > entry.idx
+            && self.trading_duration(entry_bar.datetime, exit_bar.datetime) >= activation_delay
+        {
+            let move_at_open = (exit_bar.open - entry.price) * direction;
+            if stop_price.is_some_and(|stop| move_at_open <= (stop - entry.price) * direction) {
+                return (forced_exit_idx, exit_bar.open, ExitReason::StopLoss);
+            }
+            if target_price.is_some_and(|target| move_at_open >= (target - entry.price) * direction) {
+                return (forced_exit_idx, exit_bar.open, ExitReason::TakeProfit);
+            }
+        }
+        (forced_exit_idx, exit_bar.open, ExitReason::EndOfDay)
+    }
+
+    // Maximum favorable excursion in points over the holding bars, and how many bars after
+    // entry it was first reached. `bars` runs from the entry bar to the exit bar; only the
+    // exit fill price counts for the final bar.
+    fn favorable_excursion(bars: &[&OhlcBar], entry_price: f64, exit_price: f64, signal: i32) -> (f64, usize) {
+        let direction = signal as f64;
+        let mut mfe = 0.0_f64;
+        let mut bars_to_peak = 0;
+        
+        for (offset, bar) in bars.iter().enumerate().skip(1) {
+            let best_price = if offset == bars.len() - 1 {
+                exit_price
+            } else if signal == 1 {
+                bar.high
+            } else {
+                bar.low
+            };
+            let excursion = (best_price - entry_price) * direction;
+            if excursion > mfe {
+                mfe = excursion;
+                bars_to_peak = offset;
+            }
+        }
+        
+        (mfe, bars_to_peak)
+    }
+
+    fn transaction_cost(&self, instrument: Option<&Instrument>, entry_price: f64, exit_price: f64, quantity: f64) -> f64 {
+        let rate = self.config.transaction_cost_rate;
+        match (instrument, self.config.cost_model) {
+            // Charged on the traded notional of both legs
+            (Some(i), _) => (entry_price + exit_price) * quantity * i.point_value * i.cost_bps / 10_000.0,
+            (None, CostModel::Notional) => (entry_price + exit_price) * quantity * rate,
+            // Legacy point-space model: a fraction of the price move
+            (None, CostModel::PriceMove) => (exit_price - entry_price).abs() * quantity * rate,
+        }
+    }
+
+    fn round_to_tick(price: f64, instrument: Option<&Instrument>) -> f64 {
+        match instrument {
+            Some(i) if i.tick_size > 0.0 => (price / i.tick_size).round() * i.tick_size,
+            _ => price,
+        }
+    }
+
+    // Largest whole number of lots that fits in `quantity`; the epsilon keeps exact
+    // multiples from flooring a lot short
+    fn round_to_lot(quantity: f64, instrument: Option<&Instrument>) -> f64 {
+        match instrument {
+            Some(i) if i.lot_size > 0.0 => (quantity / i.lot_size + 1e-9).floor() * i.lot_size,
+            _ => quantity,
+        }
+    }
+
+    pub fn calculate_performance_metrics(&self) -> PerformanceMetrics {
+        let step_start = Instant::now();
+        
+        // Open positions have no realized PnL yet
+        let closed: Vec<Trade> = self.trades.iter().filter(|t| !t.is_open).cloned().collect();
+        let metrics = self.metrics_for_trades(&closed, &self.trading_days(&closed, |_| true));
+        
+        eprintln!("Performance calculation completed in {:.2} seconds", step_start.elapsed().as_secs_f64());
+        metrics
+    }
+
+    // Metrics for the closed trades dated within [start, end], without re-running the backtest
+    pub fn calculate_performance_metrics_range(&self, start: NaiveDate, end: NaiveDate) -> PerformanceMetrics {
+        let in_range: Vec<Trade> = self.trades.iter()
+            .filter(|t| !t.is_open && t.date >= start && t.date <= end)
+            .cloned()
+            .collect();
+        // Exposure is measured against the range's own trading days
+        let days = self.trading_days(&in_range, |date| date >= start && date <= end);
+        self.metrics_for_trades(&in_range, &days)
+    }
+
+    // Marks each closed trade against the bars it was held through and keeps the deepest
+    // adverse excursion per session. As with MFE, only the fill counts on the exit bar.
+    pub fn session_adverse_excursion(&self) -> Option<SessionAdverseExcursion> {
+        let mut by_day: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            let (Some(exit_time), Some(exit_price)) = (trade.exit_time, trade.exit_price) else {
+                continue;
+            };
+            let direction = trade.signal as f64;
+            // Bars are sorted by datetime, so the holding period is found by binary search
+            // rather than a pass over the whole series per trade
+            let start = self.data.partition_point(|bar| bar.datetime <= trade.entry_time);
+            let end = self.data.partition_point(|bar| bar.datetime < exit_time).max(start);
+            let worst_price = self.data[start..end].iter()
+                .filter(|bar| bar.date == trade.date)
+                .map(|bar| if trade.signal == 1 { bar.low } else { bar.high })
+                .chain(std::iter::once(exit_price))
+                .fold(trade.entry_price, |worst, price| if (price - worst) * direction < 0.0 { price } else { worst });
+            let adverse = (trade.entry_price - worst_price) * direction * trade.quantity * trade.point_value;
+            let day = by_day.entry(trade.date).or_insert(0.0);
+            *day = day.max(adverse);
+        }
+        if by_day.is_empty() {
+            return None;
+        }
+        
+        let average = by_day.values().sum::<f64>() / by_day.len() as f64;
+        let worst = by_day.values().copied().fold(0.0, f64::max);
+        Some(SessionAdverseExcursion { by_day, average, worst })
+    }
+
+    pub fn metrics_by_exit_reason(&self) -> BTreeMap<ExitReason, ExitReasonStats> {
+        let mut breakdown: BTreeMap<ExitReason, ExitReasonStats> = BTreeMap::new();
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            let stats = breakdown.entry(trade.exit_reason)
+                .or_insert(ExitReasonStats { trades: 0, avg_bars_held: 0.0, total_pnl: 0.0 });
+            stats.trades += 1;
+            stats.avg_bars_held += trade.bars_held as f64;
+            stats.total_pnl += trade.net_pnl;
+        }
+        for stats in breakdown.values_mut() {
+            stats.avg_bars_held /= stats.trades as f64;
+        }
+        breakdown
+    }
+
+    // Metrics over each tag's trades; untagged trades are left out
+    pub fn metrics_by_tag(&self) -> BTreeMap<String, PerformanceMetrics> {
+        let mut groups: BTreeMap<String, Vec<Trade>> = BTreeMap::new();
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            if let Some(tag) = &trade.tag {
+                groups.entry(tag.clone()).or_default().push(trade.clone());
+            }
+        }
+        groups.into_iter()
+            .map(|(tag, trades)| {
+                // A tag is only exposed to the market on the days it traded
+                let traded: BTreeSet<NaiveDate> = trades.iter().map(|t| t.date).collect();
+                let days = self.trading_days(&trades, |date| traded.contains(&date));
+                let metrics = self.metrics_for_trades(&trades, &days);
+                (tag, metrics)
+            })
+            .collect()
+    }
+
+    fn metrics_for_trades(&self, trades: &[Trade], trading_days: &[NaiveDate]) -> PerformanceMetrics {
+        if trades.is_empty() {
+            return PerformanceMetrics {
+                total_pnl: 0.0,
+                max_drawdown: 0.0,
+                sharpe_ratio: 0.0,
+                sortino_ratio: 0.0,
+                calmar_ratio: 0.0,
+                win_rate: 0.0,
+                avg_win: 0.0,
+                avg_loss: 0.0,
+                exposure_pct: 0.0,
+                avg_bars_to_peak: 0.0,
+                cost_flipped_trades: 0,
+                expectancy_bps: 0.0,
+                profitable_days_pct: 0.0,
+                cagr_pct: 0.0,
+                mar_ratio: 0.0,
+                total_return_pct: 0.0,
+                capital_cagr_pct: 0.0,
+                max_consecutive_wins: 0,
+                max_consecutive_losses: 0,
+                total_trades: 0,
+            };
+        }
+
+        let total_pnl: f64 = trades.iter().map(|t| t.net_pnl).sum();
+        
+        // Calculate cumulative PnL and drawdown
+        let mut cum_pnl = 0.0_f64;
+        let mut running_max = 0.0_f64;
+        let mut max_drawdown = 0.0_f64;
+        
+        for trade in trades {
+            cum_pnl += trade.net_pnl;
+            running_max = running_max.max(cum_pnl);
+            let drawdown = cum_pnl - running_max;
+            max_drawdown = max_drawdown.min(drawdown);
+        }
+
+        // Calculate statistics
+        let pnl_values: Vec<f64> = trades.iter().map(|t| t.net_pnl).collect();
+        let mean_pnl = total_pnl / trades.len() as f64;
+        
+        let std_dev = self.std_dev(&pnl_values);
+        
+        let sharpe_ratio = if std_dev != 0.0 { mean_pnl / std_dev } else { 0.0 };
+        
+        let losses: Vec<f64> = pnl_values.iter().copied().filter(|pnl| *pnl < 0.0).collect();
+        let downside_std = self.std_dev(&losses);
+        let sortino_ratio = if downside_std != 0.0 { mean_pnl / downside_std } else { 0.0 };
+        let calmar_ratio = if max_drawdown != 0.0 { mean_pnl / max_drawdown.abs() } else { 0.0 };
+
+        // Win rate and average win/loss
+        let winning_trades: Vec<&Trade> = trades.iter().filter(|t| t.net_pnl > 0.0).collect();
+        let losing_trades: Vec<&Trade> = trades.iter().filter(|t| t.net_pnl < 0.0).collect();
+        
+        let win_rate_trades = match self.config.win_rate_basis {
+            WinRateBasis::AllTrades => trades.len(),
+            WinRateBasis::NonScratchTrades => winning_trades.len() + losing_trades.len(),
+        };
+        let win_rate = if win_rate_trades > 0 {
+            (winning_trades.len() as f64 / win_rate_trades as f64) * 100.0
+        } else { 0.0 };
+        let avg_win = if !winning_trades.is_empty() {
+            winning_trades.iter().map(|t| t.net_pnl).sum::<f64>() / winning_trades.len() as f64
+        } else { 0.0 };
+        let avg_loss = if !losing_trades.is_empty() {
+            losing_trades.iter().map(|t| t.net_pnl).sum::<f64>() / losing_trades.len() as f64
+        } else { 0.0 };
+        let avg_bars_to_peak = if !winning_trades.is_empty() {
+            winning_trades.iter().map(|t| t.bars_to_peak as f64).sum::<f64>() / winning_trades.len() as f64
+        } else { 0.0 };
+
+        let cost_flipped_trades = trades.iter()
+            .filter(|t| t.gross_pnl > 0.0 && t.net_pnl < 0.0)
+            .count();
+
+        let expectancy_bps = trades.iter().map(|t| t.return_pct() * 10_000.0).sum::<f64>() / trades.len() as f64;
+
+        let daily = Self::group_daily_pnl(trades);
+        let profitable_days_pct = daily.values().filter(|pnl| **pnl > 0.0).count() as f64 / daily.len() as f64 * 100.0;
+
+        let exposure_pct = self.exposure_pct(trades, trading_days);
+        let (cagr_pct, mar_ratio) = self.mar_ratio(trades);
+        let (total_return_pct, capital_cagr_pct) = self.capital_returns(trades, total_pnl);
+        
+        let (mut wins_run, mut losses_run) = (0, 0);
+        let (mut max_consecutive_wins, mut max_consecutive_losses) = (0, 0);
+        for trade in trades {
+            if trade.net_pnl > 0.0 {
+                wins_run += 1;
+                losses_run = 0;
+            } else if trade.net_pnl < 0.0 {
+                losses_run += 1;
+                wins_run = 0;
+            } else {
+                wins_run = 0;
+                losses_run = 0;
+            }
+            max_consecutive_wins = max_consecutive_wins.max(wins_run);
+            max_consecutive_losses = max_consecutive_losses.max(losses_run);
+        }
+
+        PerformanceMetrics {
+            total_pnl,
+            max_drawdown,
+            sharpe_ratio,
+            sortino_ratio,
+            calmar_ratio,
+            win_rate,
+            avg_win,
+            avg_loss,
+            exposure_pct,
+            avg_bars_to_peak,
+            cost_flipped_trades,
+            expectancy_bps,
+            profitable_days_pct,
+            cagr_pct,
+            mar_ratio,
+            total_return_pct,
+            capital_cagr_pct,
+            max_consecutive_wins,
+            max_consecutive_losses,
+            total_trades: trades.len(),
+        }.clamp_non_finite()
+    }
+
+    // Standard deviation with std_ddof degrees of freedom removed; 0.0 when too few values
+    fn std_dev(&self, values: &[f64]) -> f64 {
+        if values.len() <= self.config.std_ddof {
+            return 0.0;
+        }
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let sum_sq: f64 = values.iter().map(|x| (x - mean).powi(2)).sum();
+        (sum_sq / (values.len() - self.config.std_ddof) as f64).sqrt()
+    }
+
+    // CAGR in percent over the trade date span and the MAR ratio (CAGR over the max
+    // drawdown percentage), both from compounding each trade's percent return
+    fn mar_ratio(&self, trades: &[Trade]) -> (f64, f64) {
+        let Some(last_date) = trades.iter().map(|t| t.date).max() else {
+            return (0.0, 0.0);
+        };
+        let period_start = self.config.mar_period_years
+            .map(|years| last_date - chrono::Duration::days((years * 365.25).round() as i64));
+        let trades: Vec<&Trade> = trades.iter()
+            .filter(|t| period_start.is_none_or(|start| t.date >= start))
+            .collect();
+        let first_date = trades.iter().map(|t| t.date).min().unwrap_or(last_date);
+        let years = (last_date - first_date).num_days() as f64 / 365.25;
+        if years < 1.0 {
+            return (0.0, 0.0);
+        }
+        
+        let mut equity = 1.0_f64;
+        let mut peak = 1.0_f64;
+        let mut max_drawdown_pct = 0.0_f64;
+        for trade in &trades {
+            equity *= 1.0 + trade.return_pct();
+            peak = peak.max(equity);
+            max_drawdown_pct = max_drawdown_pct.max((peak - equity) / peak * 100.0);
+        }
+        
+        let cagr_pct = (equity.powf(1.0 / years) - 1.0) * 100.0;
+        let mar_ratio = if max_drawdown_pct > 0.0 { cagr_pct / max_drawdown_pct } else { 0.0 };
+        (cagr_pct, mar_ratio)
+    }
+
+    // Total and annualized return on initial_capital in percent. A single trading date has no
+    // span to annualize over, so its CAGR is 0.0.
+    fn capital_returns(&self, trades: &[Trade], total_pnl: f64) -> (f64, f64) {
+        let capital = self.config.initial_capital;
+        if capital <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let growth = (capital + total_pnl) / capital;
+        let total_return_pct = (growth - 1.0) * 100.0;
+        
+        let first_date = trades.iter().map(|t| t.date).min();
+        let last_date = trades.iter().map(|t| t.date).max();
+        let days = match (first_date, last_date) {
+            (Some(first), Some(last)) => (last - first).num_days(),
+            _ => 0,
+        };
+        let capital_cagr_pct = if days == 0 {
+            0.0
+        } else if growth <= 0.0 {
+            // Capital wiped out
+            -100.0
+        } else {
+            (growth.powf(365.25 / days as f64) - 1.0) * 100.0
+        };
+        (total_return_pct, capital_cagr_pct)
+    }
+
+    // Closed trades restated on initial_capital; None without a starting capital. Each day's
+    // return is its net PnL over the equity it started with.
+    pub fn annualized_report(&self) -> Option<AnnualizedReport> {
+        let capital = self.config.initial_capital;
+        if capital <= 0.0 {
+            return None;
+        }
+        let closed: Vec<Trade> = self.trades.iter().filter(|t| !t.is_open).cloned().collect();
+        let total_pnl: f64 = closed.iter().map(|t| t.net_pnl).sum();
+        let (total_return_pct, annualized_return_pct) = self.capital_returns(&closed, total_pnl);
+        
+        let mut equity = capital;
+        let mut peak = capital;
+        let mut max_drawdown_pct = 0.0_f64;
+        let mut daily_returns = Vec::new();
+        for pnl in Self::group_daily_pnl(&closed).into_values() {
+            daily_returns.push(pnl / equity);
+            equity += pnl;
+            peak = peak.max(equity);
+            max_drawdown_pct = max_drawdown_pct.max((peak - equity) / peak * 100.0);
+        }
+        let annualized_volatility_pct = self.std_dev(&daily_returns) * 252.0_f64.sqrt() * 100.0;
+        
+        Some(AnnualizedReport {
+            total_return_pct,
+            annualized_return_pct,
+            annualized_volatility_pct,
+            max_drawdown_pct,
+            sharpe_ratio: if annualized_volatility_pct > 0.0 { annualized_return_pct / annualized_volatility_pct } else { 0.0 },
+            calmar_ratio: if max_drawdown_pct > 0.0 { annualized_return_pct / max_drawdown_pct } else { 0.0 },
+        })
+    }
+
+    // Cumulative net PnL after each closed trade in exit-time order, preceded by a 0.0 point
+    // at the first entry
+    pub fn equity_curve(&self) -> Vec<(NaiveDateTime, f64)> {
+        let mut closed: Vec<(NaiveDateTime, &Trade)> = self.trades.iter()
+            .filter_map(|t| t.exit_time.map(|exit_time| (exit_time, t)))
+            .collect();
+        closed.sort_by_key(|(exit_time, _)| *exit_time);
+        let Some(start) = closed.iter().map(|(_, t)| t.entry_time).min() else {
+            return Vec::new();
+        };
+        
+        let mut equity = 0.0;
+        let mut curve = vec![(start, equity)];
+        for (exit_time, trade) in closed {
+            equity += trade.net_pnl;
+            curve.push((exit_time, equity));
+        }
+        curve
+    }
+
+    // Growth of one unit of capital compounding each trade's percent return, starting at
+    // 1.0 before the first trade; comparable across instruments at different price levels
+    pub fn percent_return_curve(&self) -> Vec<f64> {
+        let mut equity = 1.0;
+        let mut curve = vec![equity];
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            equity *= 1.0 + trade.return_pct();
+            curve.push(equity);
+        }
+        curve
+    }
+
+    // Net PnL summed per trading date, over closed trades
+    pub fn daily_pnl(&self) -> BTreeMap<NaiveDate, f64> {
+        let closed: Vec<Trade> = self.trades.iter().filter(|t| !t.is_open).cloned().collect();
+        Self::group_daily_pnl(&closed)
+    }
+
+    // Net PnL per (year, month) and per year, in chronological order
+    pub fn monthly_pnl(&self) -> BTreeMap<(i32, u32), f64> {
+        let mut monthly: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            *monthly.entry((trade.date.year(), trade.date.month())).or_insert(0.0) += trade.net_pnl;
+        }
+        monthly
+    }
+
+    pub fn yearly_pnl(&self) -> BTreeMap<i32, f64> {
+        let mut yearly: BTreeMap<i32, f64> = BTreeMap::new();
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            *yearly.entry(trade.date.year()).or_insert(0.0) += trade.net_pnl;
+        }
+        yearly
+    }
+
+    fn group_daily_pnl(trades: &[Trade]) -> BTreeMap<NaiveDate, f64> {
+        let mut daily: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for trade in trades {
+            *daily.entry(trade.date).or_insert(0.0) += trade.net_pnl;
+        }
+        daily
+    }
+
+    // Mean active return over tracking error, where active return is the strategy's daily
+    // PnL minus the benchmark's return on the same date. Days without trades count as flat.
+    pub fn compute_information_ratio(&self, benchmark_returns: &BTreeMap<NaiveDate, f64>) -> f64 {
+        if benchmark_returns.is_empty() {
+            return 0.0;
+        }
+        
+        let daily = self.daily_pnl();
+        let active: Vec<f64> = benchmark_returns.iter()
+            .map(|(date, benchmark)| daily.get(date).copied().unwrap_or(0.0) - benchmark)
+            .collect();
+        
+        let mean = active.iter().sum::<f64>() / active.len() as f64;
+        let tracking_error = (active.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / active.len() as f64).sqrt();
+        
+        if tracking_error != 0.0 { mean / tracking_error } else { 0.0 }
+    }
+
+    // Pearson correlation of daily PnL with the benchmark over each trailing `window` of
+    // benchmark dates, keyed by the window's last date. Nothing is emitted until the first
+    // window is full; days without trades count as flat.
+    pub fn rolling_correlation(&self, benchmark_returns: &BTreeMap<NaiveDate, f64>, window: usize) -> Vec<(NaiveDate, f64)> {
+        if window == 0 {
+            return Vec::new();
+        }
+        let daily = self.daily_pnl();
+        let series: Vec<(NaiveDate, f64, f64)> = benchmark_returns.iter()
+            .map(|(date, benchmark)| (*date, daily.get(date).copied().unwrap_or(0.0), *benchmark))
+            .collect();
+        
+        series.windows(window)
+            .map(|days| {
+                let n = days.len() as f64;
+                let mean_x = days.iter().map(|d| d.1).sum::<f64>() / n;
+                let mean_y = days.iter().map(|d| d.2).sum::<f64>() / n;
+                let cov: f64 = days.iter().map(|d| (d.1 - mean_x) * (d.2 - mean_y)).sum();
+                let var_x: f64 = days.iter().map(|d| (d.1 - mean_x).powi(2)).sum();
+                let var_y: f64 = days.iter().map(|d| (d.2 - mean_y).powi(2)).sum();
+                let denominator = (var_x * var_y).sqrt();
+                let correlation = if denominator != 0.0 { cov / denominator } else { 0.0 };
+                (days[days.len() - 1].0, correlation)
+            })
+            .collect()
+    }
+
+    pub fn save_rolling_correlation(&self, output_path: &str, benchmark_returns: &BTreeMap<NaiveDate, f64>, window: usize) -> Result<()> {
+        let mut wtr = csv::Writer::from_path(output_path)?;
+        wtr.write_record(["date", "correlation"])?;
+        for (date, correlation) in self.rolling_correlation(benchmark_returns, window) {
+            wtr.write_record([date.to_string(), format!("{:.6}", correlation)])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    // Distinct bar dates passing `in_scope`; falls back to the trade dates if bars were not kept
+    fn trading_days(&self, trades: &[Trade], in_scope: impl Fn(NaiveDate) -> bool) -> Vec<NaiveDate> {
+        let mut days: Vec<NaiveDate> = self.data.iter().map(|b| b.date).filter(|date| in_scope(*date)).collect();
+        if self.data.is_empty() {
+            days = trades.iter().map(|t| t.date).collect();
+        }
+        days.dedup();
+        days
+    }
+
+    fn exposure_pct(&self, trades: &[Trade], trading_days: &[NaiveDate]) -> f64 {
+        let session_start = NaiveDate::MIN.and_time(self.config.session_start);
+        let session_end = NaiveDate::MIN.and_time(self.config.session_end);
+        let session_minutes = self.trading_duration(session_start, session_end).num_seconds() as f64 / 60.0;
+        let available_minutes = trading_days.len() as f64 * session_minutes;
+        if available_minutes <= 0.0 {
+            return 0.0;
+        }
+        
+        let position_minutes: f64 = trades.iter()
+            .filter_map(|t| t.exit_time.map(|exit_time| self.trading_duration(t.entry_time, exit_time)))
+            .map(|held| held.num_seconds() as f64 / 60.0)
+            .sum();
+        position_minutes / available_minutes * 100.0
+    }
+
+    // Elapsed time between two instants, excluding any configured session breaks
+    fn trading_duration(&self, start: NaiveDateTime, end: NaiveDateTime) -> chrono::Duration {
+        let mut duration = end - start;
+        for day in start.date().iter_days().take_while(|day| *day <= end.date()) {
+            for (break_start, break_end) in &self.config.session_breaks {
+                let overlap_start = day.and_time(*break_start).max(start);
+                let overlap_end = day.and_time(*break_end).min(end);
+                if overlap_end > overlap_start {
+                    duration -= overlap_end - overlap_start;
+                }
+            }
+        }
+        duration
+    }
+
+    // Writes the trades CSV; "-" sends it to stdout, leaving stderr for progress and the summary
+    pub fn save_results(&self, output_path: &str) -> Result<()> {
+        if output_path == "-" {
+            let mut wtr = csv::Writer::from_writer(std::io::stdout().lock());
+            self.write_trades(&mut wtr)?;
+            wtr.flush()?;
+            return Ok(());
+        }
+        let file = File::create(output_path)?;
+        
+        if output_path.ends_with(".gz") {
+            let mut wtr = csv::Writer::from_writer(GzEncoder::new(file, Compression::default()));
+            self.write_trades(&mut wtr)?;
+            wtr.into_inner().map_err(|e| e.into_error())?.finish()?;
+        } else {
+            let mut wtr = csv::Writer::from_writer(file);
+            self.write_trades(&mut wtr)?;
+            wtr.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn save_results_json(&self, output_path: &str) -> Result<()> {
+        let document = ResultsDocument {
+            trades: self.trades.clone(),
+            metrics: self.calculate_performance_metrics(),
+        };
+        serde_json::to_writer_pretty(std::io::BufWriter::new(File::create(output_path)?), &document)?;
+        Ok(())
+    }
+
+    fn write_trades<W: Write>(&self, wtr: &mut csv::Writer<W>) -> Result<()> {
+        // Write header
+        let mut header = vec![
+            "date", "entry_time", "entry_price", "exit_time", 
+            "exit_price", "signal", "gross_pnl", "net_pnl", "exit_reason"
+        ];
+        if self.config.extended_output {
+            header.extend([
+                "entry_open", "entry_high", "entry_low", "entry_close", "entry_volume",
+                "exit_open", "exit_high", "exit_low", "exit_close", "exit_volume",
+            ]);
+        }
+        wtr.write_record(&header)?;
+        
+        // Write data
+        for trade in &self.trades {
+            // Open positions have no exit or realized PnL to report
+            let closed_field = |value: String| if trade.is_open { String::new() } else { value };
+            let mut record = vec![
+                trade.date.to_string(),
+                self.format_timestamp(trade.entry_time),
+                trade.entry_price.to_string(),
+                trade.exit_time.map_or_else(String::new, |time| self.format_timestamp(time)),
+                trade.exit_price.map_or_else(String::new, |price| price.to_string()),
+                trade.signal.to_string(),
+                closed_field(format!("{:.4}", trade.gross_pnl)),
+                closed_field(format!("{:.4}", trade.net_pnl)),
+                closed_field(format!("{:?}", trade.exit_reason)),
+            ];
+            if self.config.extended_output {
+                for bar in [Some(trade.entry_bar_ohlcv), trade.exit_bar_ohlcv] {
+                    let values = bar.map(|bar| [bar.open, bar.high, bar.low, bar.close, bar.volume]);
+                    record.extend((0..5).map(|i| values.map_or_else(String::new, |values| values[i].to_string())));
+                }
+            }
+            wtr.write_record(&record)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_equity_curve(&self, output_path: &str) -> Result<()> {
+        let mut wtr = csv::Writer::from_path(output_path)?;
+        wtr.write_record(["time", "equity"])?;
+        for (time, equity) in self.equity_curve() {
+            wtr.write_record([self.format_timestamp(time), format!("{:.4}", equity)])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn save_html_report(&self, output_path: &str) -> Result<()> {
+        let metrics = self.calculate_performance_metrics();
+        
+        let rows = [
+            ("Total Trades", metrics.total_trades.to_string()),
+            ("Total PnL", format!("{:.2}", metrics.total_pnl)),
+            ("Max Drawdown", format!("{:.2}", metrics.max_drawdown)),
+            ("Sharpe Ratio", format!("{:.4}", metrics.sharpe_ratio)),
+            ("Sortino Ratio", format!("{:.4}", metrics.sortino_ratio)),
+            ("Calmar Ratio", format!("{:.4}", metrics.calmar_ratio)),
+            ("Win Rate", format!("{:.1}%", metrics.win_rate)),
+            ("Average Win", format!("{:.2}", metrics.avg_win)),
+            ("Average Loss", format!("{:.2}", metrics.avg_loss)),
+            ("Exposure", format!("{:.1}%", metrics.exposure_pct)),
+            ("Average Bars To Peak (winners)", format!("{:.1}", metrics.avg_bars_to_peak)),
+        ];
+        let table_rows: String = rows.iter()
+            .map(|(name, value)| format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value))
+            .collect();
+        
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} ORB Report</title>\n\
+             <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+             th,td{{border:1px solid #ccc;padding:4px 12px;text-align:left}}</style>\n</head>\n<body>\n\
+             <h1>{} Opening Range Breakout</h1>\n<table>\n{}</table>\n<h2>Equity Curve</h2>\n{}\n</body>\n</html>\n",
+            self.config.symbol, self.config.symbol, table_rows, self.equity_curve_svg(800.0, 300.0),
+        );
+        
+        std::fs::write(output_path, html)?;
+        Ok(())
+    }
+
+    fn equity_curve_svg(&self, width: f64, height: f64) -> String {
+        // Cumulative net PnL, starting flat before the first trade
+        let mut cum_pnl = 0.0;
+        let mut equity = vec![0.0];
+        for trade in self.trades.iter().filter(|t| !t.is_open) {
+            cum_pnl += trade.net_pnl;
+            equity.push(cum_pnl);
+        }
+        
+        let min = equity.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = equity.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let range = if max > min { max - min } else { 1.0 };
+        let step = width / (equity.len() - 1).max(1) as f64;
+        
+        let points: Vec<String> = equity.iter()
+            .enumerate()
+            .map(|(i, value)| format!("{:.1},{:.1}", i as f64 * step, height - (value - min) / range * height))
+            .collect();
+        
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+             <polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1.5\" points=\"{}\"/></svg>",
+            points.join(" "), w = width, h = height,
+        )
+    }
+
+    fn format_timestamp(&self, datetime: NaiveDateTime) -> String {
+        let datetime = match self.config.timestamp_convention {
+            TimestampConvention::BarStart => datetime,
+            TimestampConvention::BarClose => datetime + self.bar_interval(),
+        };
+        match self.config.output_utc_offset {
+            Some(offset) => offset.from_local_datetime(&datetime).unwrap().to_rfc3339(),
+            None => datetime.to_string(),
+        }
+    }
+}
+
+// Amount at pnl_precision decimals with the configured currency symbol and digit grouping,
+// e.g. -₹1,23,456.78
+pub fn format_money(amount: f64, config: &StrategyConfig) -> String {
+    let formatted = format!("{:.*}", config.pnl_precision, amount.abs());
+    let (int_part, frac_part) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    
+    // Group sizes from the right: Indian keeps three digits, then pairs
+    let (first, rest) = match config.digit_grouping {
+        DigitGrouping::None => (usize::MAX, usize::MAX),
+        DigitGrouping::Standard => (3, 3),
+        DigitGrouping::Indian => (3, 2),
+    };
+    let digits: Vec<char> = int_part.chars().collect();
+    let mut groups: Vec<String> = Vec::new();
+    let mut end = digits.len();
+    let mut size = first;
+    while end > 0 {
+        let start = end.saturating_sub(size);
+        groups.push(digits[start..end].iter().collect());
+        end = start;
+        size = rest;
+    }
+    groups.reverse();
+    
+    // No sign on amounts that round to zero
+    let sign = if amount < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') { "-" } else { "" };
+    let frac = if frac_part.is_empty() { String::new() } else { format!(".{}", frac_part) };
+    format!("{}{}{}{}", sign, config.currency_symbol, groups.join(","), frac)
+}
+
+pub fn print_summary(metrics: &PerformanceMetrics, config: &StrategyConfig, total_time: f64) {
+    eprintln!("{}", format_summary(metrics, config, total_time));
+}
+
+pub fn format_summary(metrics: &PerformanceMetrics, config: &StrategyConfig, total_time: f64) -> String {
+    let ratio = config.ratio_precision;
+    let mut lines = vec![
+        format!("\n{}", "=".repeat(50)),
+        "TRADING STRATEGY RESULTS".to_string(),
+        "=".repeat(50),
+        format!("Total Execution Time: {:.2} seconds", total_time),
+        format!("Total Trades: {}", metrics.total_trades),
+        format!("Total PnL: {}", format_money(metrics.total_pnl, config)),
+        format!("Max Drawdown: {}", format_money(metrics.max_drawdown, config)),
+        format!("Sharpe Ratio: {:.*}", ratio, metrics.sharpe_ratio),
+        format!("Sortino Ratio: {:.*}", ratio, metrics.sortino_ratio),
+        format!("Calmar Ratio: {:.*}", ratio, metrics.calmar_ratio),
+        format!("Win Rate: {:.1}%", metrics.win_rate),
+        format!("Average Win: {}", format_money(metrics.avg_win, config)),
+        format!("Average Loss: {}", format_money(metrics.avg_loss, config)),
+        format!("Exposure: {:.1}%", metrics.exposure_pct),
+        format!("Average Bars To Peak (winners): {:.1}", metrics.avg_bars_to_peak),
+        format!("Cost-Flipped Trades: {}", metrics.cost_flipped_trades),
+        format!("Expectancy: {:.2} bps", metrics.expectancy_bps),
+        format!("Profitable Days: {:.1}%", metrics.profitable_days_pct),
+        format!("CAGR: {:.2}%", metrics.cagr_pct),
+        format!("MAR Ratio: {:.*}", ratio, metrics.mar_ratio),
+        format!("Max Consecutive Wins: {}", metrics.max_consecutive_wins),
+        format!("Max Consecutive Losses: {}", metrics.max_consecutive_losses),
+    ];
+    if config.initial_capital > 0.0 {
+        lines.push(format!("Total Return: {:.2}%", metrics.total_return_pct));
+        lines.push(format!("CAGR on Capital: {:.2}%", metrics.capital_cagr_pct));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategy_initialization() {
+        let strategy = NiftyStrategy::new();
+        assert_eq!(strategy.trades.len(), 0);
+        assert_eq!(strategy.data.len(), 0);
+    }
+
+    #[test]
+    fn test_performance_metrics_empty() {
+        let strategy = NiftyStrategy::new();
+        let metrics = strategy.calculate_performance_metrics();
+        assert_eq!(metrics.total_trades, 0);
+        assert_eq!(metrics.total_pnl, 0.0);
+    }
+
+    #[test]
+    fn test_datetime_parsing() {
+        let test_cases = [
+            "2024-01-15 09:30:00",
+            "15-01-2024 09:30:00",
+            "2024/01/15 09:30:00",
+        ];
+        
+        for case in &test_cases {
+            assert!(NiftyStrategy::parse_datetime(case).is_some());
+        }
+    }
+
+    #[test]
+    fn test_epoch_datetime_parsing() {
+        let expected = NaiveDateTime::parse_from_str("2024-01-15 03:45:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(NiftyStrategy::parse_datetime("1705290300"), Some(expected));
+        assert_eq!(NiftyStrategy::parse_datetime("1705290300000"), Some(expected));
+        // A year or other small number is not an epoch
+        assert_eq!(NiftyStrategy::parse_datetime("2015"), None);
+        assert_eq!(NiftyStrategy::parse_datetime("20240115"), None);
+    }
+
+    #[test]
+    fn test_5min_rounding() {
+        let datetime = NaiveDateTime::parse_from_str("2024-01-15 09:37:23", "%Y-%m-%d %H:%M:%S").unwrap();
+        let rounded = NiftyStrategy::round_to_timeframe(datetime, 5);
+        
+        assert_eq!(rounded.minute(), 35);
+        assert_eq!(rounded.second(), 0);
+    }
+
+    #[test]
+    fn test_configurable_timeframe() {
+        let datetime = NaiveDateTime::parse_from_str("2024-01-15 09:37:23", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(NiftyStrategy::round_to_timeframe(datetime, 15).minute(), 30);
+        assert_eq!(NiftyStrategy::round_to_timeframe(datetime, 60).minute(), 0);
+        assert_eq!(NiftyStrategy::round_to_timeframe(datetime, 1).minute(), 37);
+
+        let mut contents = String::from("date,open,high,low,close,volume\n");
+        for minute in 15..45 {
+            contents.push_str(&format!("2024-01-15 09:{}:00,100,101,99,100.5,10\n", minute));
+        }
+        let config = StrategyConfig { timeframe_minutes: 15, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(contents.as_bytes()).unwrap();
+        let starts: Vec<u32> = strategy.data.iter().map(|bar| bar.time.minute()).collect();
+        assert_eq!(starts, vec![15, 30]);
+        assert!(strategy.data.iter().all(|bar| bar.source_count == 15));
+
+        for minutes in [0, 7] {
+            let config = StrategyConfig { timeframe_minutes: minutes, ..StrategyConfig::default() };
+            let err = NiftyStrategy::with_config(config).load_from_bytes(contents.as_bytes()).unwrap_err();
+            assert!(err.to_string().contains("divisor of 60"), "{}", err);
+        }
+    }
+
+    fn test_trade(date: &str, entry: &str, exit: &str, signal: i32, net_pnl: f64) -> Trade {
+        Trade {
+            symbol: "NIFTY".to_string(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            entry_time: NaiveDateTime::parse_from_str(entry, "%Y-%m-%d %H:%M:%S").unwrap(),
+            entry_price: 100.0,
+            exit_time: Some(NaiveDateTime::parse_from_str(exit, "%Y-%m-%d %H:%M:%S").unwrap()),
+            exit_price: Some(100.0 + signal as f64 * net_pnl),
+            exit_reason: ExitReason::EndOfDay,
+            quantity: 1.0,
+            point_value: 1.0,
+            signal,
+            gross_pnl: net_pnl,
+            net_pnl,
+            mfe: net_pnl.max(0.0),
+            bars_to_peak: 0,
+            bars_held: 0,
+            is_open: false,
+            tag: None,
+            entry_bar_ohlcv: BarOhlcv::default(),
+            exit_bar_ohlcv: Some(BarOhlcv::default()),
+        }
+    }
+
+    fn test_bar(datetime: &str, open: f64, high: f64, low: f64, close: f64) -> OhlcBar {
+        let datetime = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S").unwrap();
+        OhlcBar {
+            datetime,
+            date: datetime.date(),
+            time: datetime.time(),
+            open,
+            high,
+            low,
+            close,
+            volume: 1000.0,
+            candle_type: None,
+            candle_val: None,
+            candle_high: None,
+            candle_low: None,
+            candle_body_ratio: None,
+            wick_ratio: None,
+            source_count: 1,
+            synthetic: false,
+            session_high: high,
+            session_low: low,
+            atr: None,
+            signal: 0,
+        }
+    }
+
+    // One trading day: bullish 9:25 signal candle (high 102), breakout close 103 at 9:30,
+    // flat drift to the 15:15 exit bar which opens at `exit_open`
+    fn breakout_day(date: &str, exit_open: f64) -> Vec<OhlcBar> {
+        vec![
+            test_bar(&format!("{} 09:25:00", date), 100.0, 102.0, 99.0, 101.0),
+            test_bar(&format!("{} 09:30:00", date), 101.0, 103.5, 100.5, 103.0),
+            test_bar(&format!("{} 09:35:00", date), 103.0, 104.0, 102.5, 103.5),
+            test_bar(&format!("{} 15:15:00", date), exit_open, exit_open + 1.0, exit_open - 1.0, exit_open),
+        ]
+    }
+
+    fn run_pipeline(strategy: &mut NiftyStrategy, bars: Vec<OhlcBar>) {
+        strategy.load_bars(bars);
+        strategy.identify_signal_candles().unwrap();
+        strategy.generate_trading_signals().unwrap();
+        strategy.identify_trades().unwrap();
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("orb_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_save_results_with_utc_offset() {
+        let config = StrategyConfig {
+            output_utc_offset: FixedOffset::east_opt(5 * 3600 + 30 * 60),
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 10.0));
+
+        let path = temp_path("utc_offset.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(contents.contains("2024-01-15T09:35:00+05:30"));
+        assert!(contents.contains("2024-01-15T15:15:00+05:30"));
+    }
+
+    #[test]
+    fn test_save_results_naive_by_default() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 10.0));
+
+        let path = temp_path("naive.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(contents.contains("2024-01-15 09:35:00"));
+        assert!(!contents.contains("+05:30"));
+    }
+
+    // Reference two-pass implementation: build a date map, then apply it
+    fn two_pass_signal_candles(data: &mut [OhlcBar]) {
+        let target_time = NaiveTime::from_hms_opt(9, 25, 0).unwrap();
+        let mut signal_map: HashMap<NaiveDate, (String, f64)> = HashMap::new();
+        for bar in data.iter() {
+            if bar.time == target_time
+                && let Some(classified) = NiftyStrategy::classify_signal_candle(bar, DojiPolicy::Bearish, 0.0)
+            {
+                signal_map.entry(bar.date).or_insert(classified);
+            }
+        }
+        for bar in data.iter_mut() {
+            if let Some((candle_type, candle_val)) = signal_map.get(&bar.date) {
+                bar.candle_type = Some(candle_type.clone());
+                bar.candle_val = Some(*candle_val);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_pass_signal_candles_match_two_pass() {
+        let bars = vec![
+            test_bar("2024-01-15 09:20:00", 100.0, 101.0, 99.0, 100.5),
+            test_bar("2024-01-15 09:25:00", 100.5, 102.0, 100.0, 101.5),
+            test_bar("2024-01-15 09:30:00", 101.5, 103.0, 101.0, 102.5),
+            test_bar("2024-01-16 09:25:00", 102.0, 102.5, 100.0, 100.5),
+            test_bar("2024-01-16 09:30:00", 100.5, 101.0, 99.0, 99.5),
+            test_bar("2024-01-17 09:30:00", 99.5, 100.0, 98.0, 98.5),
+        ];
+
+        let mut expected = bars.clone();
+        two_pass_signal_candles(&mut expected);
+
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(bars);
+        strategy.identify_signal_candles().unwrap();
+
+        for (actual, expected) in strategy.data.iter().zip(&expected) {
+            assert_eq!(actual.candle_type, expected.candle_type);
+            assert_eq!(actual.candle_val, expected.candle_val);
+        }
+        assert_eq!(strategy.data[0].candle_type.as_deref(), Some("bullish"));
+        assert_eq!(strategy.data[3].candle_val, Some(100.0));
+        assert_eq!(strategy.data[5].candle_type, None);
+    }
+
+    #[test]
+    fn test_load_symbols_uses_per_symbol_cost() {
+        let path = temp_path("symbols.csv");
+        std::fs::write(&path, "symbol, tick_size, lot_size, point_value, cost_bps\n\
+            NIFTY, 0.05, 1, 1, 12\n\
+            BANKNIFTY, 0.05, 1, 1, 50\n").unwrap();
+
+        let mut costs = Vec::new();
+        for symbol in ["NIFTY", "BANKNIFTY"] {
+            let config = StrategyConfig { symbol: symbol.to_string(), ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.load_symbols(path.to_str().unwrap()).unwrap();
+            assert_eq!(strategy.instruments.len(), 2);
+            run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+
+            let trade = &strategy.trades[0];
+            assert_eq!(trade.symbol, symbol);
+            costs.push(trade.gross_pnl - trade.net_pnl);
+        }
+        std::fs::remove_file(&path).ok();
+
+        // Costs are charged on the notional of both legs (103 in, 113 out)
+        assert!((costs[0] - 216.0 * 0.0012).abs() < 1e-9);
+        assert!((costs[1] - 216.0 * 0.0050).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_momentum_breakout_signal() {
+        let config = StrategyConfig {
+            signal_source: SignalSource::MomentumBreakout,
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_bars(vec![
+            test_bar("2024-01-15 10:00:00", 100.0, 101.0, 99.0, 100.5),
+            test_bar("2024-01-15 10:05:00", 100.5, 101.5, 100.0, 101.2),
+            test_bar("2024-01-15 10:10:00", 101.2, 101.4, 100.2, 100.8),
+            test_bar("2024-01-15 10:15:00", 100.8, 101.0, 99.5, 99.9),
+            test_bar("2024-01-16 10:00:00", 90.0, 91.0, 89.0, 90.5),
+        ]);
+        strategy.identify_signal_candles().unwrap();
+        strategy.generate_trading_signals().unwrap();
+
+        let signals: Vec<i32> = strategy.data.iter().map(|b| b.signal).collect();
+        // Close 101.2 > prior high 101.0 is a long; close 99.9 < prior low 100.2 is a short;
+        // the first bar of each session has no prior reference
+        assert_eq!(signals, vec![0, 1, 0, -1, 0]);
+    }
+
+    #[test]
+    fn test_exposure_pct_half_session() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(vec![test_bar("2024-01-15 09:15:00", 100.0, 101.0, 99.0, 100.5)]);
+        // 187.5 of the 375 session minutes (09:15-15:30) in a position
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:30:00", "2024-01-15 12:37:30", 1, 5.0));
+
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.exposure_pct - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_duplicate_signal_time_picks_first_bar() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:25:00", 101.0, 101.5, 98.0, 98.5),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+        ]);
+        strategy.identify_signal_candles().unwrap();
+
+        assert!(strategy.data.iter().all(|b| b.candle_type.as_deref() == Some("bullish")));
+        assert!(strategy.data.iter().all(|b| b.candle_val == Some(102.0)));
+    }
+
+    #[test]
+    fn test_aggregation_dedups_overlapping_buckets() {
+        // A second source file replays the 09:25 bucket after 09:30
+        let minute_bars = vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 101.0, 99.0, 100.5),
+            test_bar("2024-01-15 09:26:00", 100.5, 102.0, 100.0, 101.5),
+            test_bar("2024-01-15 09:30:00", 101.5, 103.0, 101.0, 102.5),
+            test_bar("2024-01-15 09:25:00", 90.0, 91.0, 89.0, 90.5),
+        ];
+        let bars = NiftyStrategy::create_bars(minute_bars, 5, VolumeAggregation::Sum);
+
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].time, NaiveTime::from_hms_opt(9, 25, 0).unwrap());
+        assert_eq!(bars[0].open, 100.0);
+        assert_eq!(bars[0].high, 102.0);
+        assert_eq!(bars[1].time, NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_notional_costs_scale_with_quantity() {
+        let path = temp_path("symbols_notional.csv");
+        std::fs::write(&path, "symbol,tick_size,lot_size,point_value,cost_bps\nNIFTY,0.05,1,25,10\n").unwrap();
+
+        let mut trades = Vec::new();
+        for quantity in [1.0, 4.0] {
+            let config = StrategyConfig { quantity, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.load_symbols(path.to_str().unwrap()).unwrap();
+            run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+            trades.push(strategy.trades[0].clone());
+        }
+        std::fs::remove_file(&path).ok();
+
+        // Same 10-point move: notional (103 + 113) x qty x 25 at 10 bps
+        let cost = |t: &Trade| t.gross_pnl - t.net_pnl;
+        assert!((cost(&trades[0]) - 216.0 * 25.0 * 0.001).abs() < 1e-9);
+        assert!((cost(&trades[1]) - 4.0 * cost(&trades[0])).abs() < 1e-9);
+        assert!((trades[1].gross_pnl - 10.0 * 4.0 * 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mixed_day_policy() {
+        // Bullish 9:25 candle (low 99) followed by a close below its low
+        let bars = vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 101.5, 98.0, 98.5),
+        ];
+
+        let mut signals = Vec::new();
+        for policy in [MixedDayPolicy::NoTrade, MixedDayPolicy::Fade] {
+            let config = StrategyConfig { mixed_day_policy: policy, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.load_bars(bars.clone());
+            strategy.identify_signal_candles().unwrap();
+            strategy.generate_trading_signals().unwrap();
+            signals.push(strategy.data[1].signal);
+        }
+
+        assert_eq!(signals, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_avg_bars_to_peak_for_winners() {
+        let mut strategy = NiftyStrategy::new();
+        // Long from 103 peaks at 107 two bars after entry, then fades into the exit
+        run_pipeline(&mut strategy, vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+            test_bar("2024-01-15 09:35:00", 103.0, 104.0, 102.5, 103.5),
+            test_bar("2024-01-15 09:40:00", 103.5, 107.0, 103.0, 106.0),
+            test_bar("2024-01-15 09:45:00", 106.0, 106.5, 104.0, 105.0),
+            test_bar("2024-01-15 15:15:00", 105.0, 105.5, 104.5, 105.0),
+        ]);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.bars_to_peak, 2);
+        assert!((trade.mfe - 4.0).abs() < 1e-9);
+
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.avg_bars_to_peak - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_volume_participation_caps_fill() {
+        let config = StrategyConfig {
+            quantity: 100.0,
+            max_volume_participation: Some(0.1),
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        // Entry bar trades only 300 units, so 10% participation allows 30
+        bars[1].volume = 300.0;
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert!((trade.quantity - 30.0).abs() < 1e-9);
+        assert!((trade.gross_pnl - 10.0 * 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_html_report_contains_metrics_and_svg() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 12.5));
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", -1, -4.25));
+
+        let path = temp_path("report.html");
+        strategy.save_html_report(path.to_str().unwrap()).unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(html.contains("<td>8.25</td>"));
+        assert!(html.contains("<svg"));
+        assert!(html.contains("<polyline"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_doji_policy_classification() {
+        let doji = test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 100.0);
+
+        let cases = [
+            (DojiPolicy::Bullish, Some(("bullish", 102.0))),
+            (DojiPolicy::Bearish, Some(("bearish", 99.0))),
+            (DojiPolicy::Skip, None),
+        ];
+        for (policy, expected) in cases {
+            let config = StrategyConfig { doji_as: policy, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.load_bars(vec![doji.clone()]);
+            strategy.identify_signal_candles().unwrap();
+
+            let bar = &strategy.data[0];
+            let actual = bar.candle_type.as_deref().zip(bar.candle_val);
+            assert_eq!(actual, expected, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_spread_round_trip_cost_for_long() {
+        let config = StrategyConfig { spread_points: 0.5, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.signal, 1);
+        assert!((trade.entry_price - 103.25).abs() < 1e-9);
+        assert!((trade.exit_price.unwrap() - 112.75).abs() < 1e-9);
+        // The 10-point move loses exactly one full spread over the round trip
+        assert!((trade.gross_pnl - (10.0 - 0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pipeline_stages_out_of_order() {
+        let mut strategy = NiftyStrategy::new();
+        let err = strategy.identify_signal_candles().unwrap_err();
+        assert!(err.to_string().contains("identify_signal_candles requires the Loaded stage"));
+
+        strategy.load_bars(breakout_day("2024-01-15", 113.0));
+        let err = strategy.generate_trading_signals().unwrap_err();
+        assert!(err.to_string().contains("generate_trading_signals requires the CandlesIdentified stage"));
+
+        // Stopping after candle identification leaves labeled bars to inspect
+        strategy.identify_signal_candles().unwrap();
+        assert!(strategy.data.iter().all(|b| b.candle_type.is_some()));
+        let err = strategy.identify_trades().unwrap_err();
+        assert!(err.to_string().contains("but the pipeline is at CandlesIdentified"));
+
+        strategy.generate_trading_signals().unwrap();
+        strategy.identify_trades().unwrap();
+        assert_eq!(strategy.trades.len(), 1);
+    }
+
+    #[test]
+    fn test_stop_activation_delay() {
+        let config = StrategyConfig {
+            stop_loss_points: Some(2.0),
+            stop_activation_delay_minutes: 15,
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        // Long entry at 103 with the stop at 101
+        run_pipeline(&mut strategy, vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+            // Spike through the stop inside the 15-minute delay window is ignored
+            test_bar("2024-01-15 09:35:00", 103.0, 103.5, 100.0, 102.5),
+            test_bar("2024-01-15 09:40:00", 102.5, 104.0, 102.0, 103.5),
+            // Once armed, the next breach exits at the stop
+            test_bar("2024-01-15 09:45:00", 103.5, 104.0, 100.5, 101.5),
+            test_bar("2024-01-15 15:15:00", 105.0, 105.5, 104.5, 105.0),
+        ]);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(9, 45, 0).unwrap());
+        assert!((trade.exit_price.unwrap() - 101.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_information_ratio() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 10.0));
+        strategy.trades.push(test_trade("2024-01-17", "2024-01-17 09:35:00", "2024-01-17 15:15:00", 1, 4.0));
+
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let benchmark: BTreeMap<NaiveDate, f64> = [
+            (date("2024-01-15"), 2.0),
+            (date("2024-01-16"), -2.0),
+            (date("2024-01-17"), 4.0),
+        ].into_iter().collect();
+
+        // Active returns 8, 2, 0: mean 10/3, population std sqrt(104/9)
+        let expected = (10.0 / 3.0) / (104.0_f64 / 9.0).sqrt();
+        assert!((strategy.compute_information_ratio(&benchmark) - expected).abs() < 1e-9);
+
+        // Identical active returns have zero tracking error
+        let flat: BTreeMap<NaiveDate, f64> = [(date("2024-01-15"), 1.0)].into_iter().collect();
+        assert_eq!(strategy.compute_information_ratio(&flat), 0.0);
+    }
+
+    #[test]
+    fn test_save_results_gzip_round_trip() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 12.5));
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 10:05:00", "2024-01-16 15:15:00", -1, -3.25));
+
+        let path = temp_path("trades.csv.gz");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+
+        let decoder = flate2::read::GzDecoder::new(File::open(&path).unwrap());
+        let mut reader = ReaderBuilder::new().has_headers(true).from_reader(decoder);
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(rows.len(), strategy.trades.len());
+        for (row, trade) in rows.iter().zip(&strategy.trades) {
+            assert_eq!(&row[0], trade.date.to_string());
+            assert_eq!(&row[1], trade.entry_time.to_string());
+            assert_eq!(row[5].parse::<i32>().unwrap(), trade.signal);
+            assert!((row[7].parse::<f64>().unwrap() - trade.net_pnl).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_entries_follow_signal_order() {
+        let mut strategy = NiftyStrategy::new();
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        // A later breakout bar supplied ahead of the earlier one must not be taken first
+        bars.insert(0, test_bar("2024-01-15 11:00:00", 104.0, 106.0, 103.5, 105.5));
+        run_pipeline(&mut strategy, bars);
+
+        assert_eq!(strategy.trades.len(), 1);
+        assert_eq!(strategy.trades[0].entry_time.time(), NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_validate_trade_order_rejects_out_of_order_entries() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 11:00:00", "2024-01-15 15:15:00", 1, 1.0));
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 10:30:00", 1, 1.0));
+
+        let err = strategy.validate_trade_order().unwrap_err();
+        assert!(err.to_string().contains("out of order"));
+
+        strategy.trades.swap(0, 1);
+        assert!(strategy.validate_trade_order().is_ok());
+    }
+
+    #[test]
+    fn test_percent_return_curve_compounds() {
+        let mut strategy = NiftyStrategy::new();
+        // +10% on a 100 entry, then -5%
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 10.0));
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, -5.0));
+
+        let curve = strategy.percent_return_curve();
+        assert_eq!(curve.len(), 3);
+        assert!((curve[0] - 1.0).abs() < 1e-12);
+        assert!((curve[1] - 1.10).abs() < 1e-12);
+        assert!((curve[2] - 1.10 * 0.95).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cost_flipped_trades_counted() {
+        let mut strategy = NiftyStrategy::new();
+        let mut flipped = test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, -0.05);
+        flipped.gross_pnl = 0.10;
+        strategy.trades.push(flipped);
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, 5.0));
+        strategy.trades.push(test_trade("2024-01-17", "2024-01-17 09:35:00", "2024-01-17 15:15:00", 1, -2.0));
+
+        let metrics = strategy.calculate_performance_metrics();
+        assert_eq!(metrics.cost_flipped_trades, 1);
+    }
+
+    #[test]
+    fn test_prior_trading_day_close_skips_weekend() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(vec![
+            test_bar("2024-01-12 15:25:00", 100.0, 101.0, 99.0, 100.5),
+            test_bar("2024-01-12 15:29:00", 100.5, 101.0, 100.0, 100.8),
+            test_bar("2024-01-15 09:15:00", 102.0, 103.0, 101.0, 102.5),
+        ]);
+
+        let monday = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert_eq!(strategy.prior_trading_day_close(monday), Some(100.8));
+        // Sunday has no data but still resolves to Friday
+        assert_eq!(strategy.prior_trading_day_close(monday.pred_opt().unwrap()), Some(100.8));
+        assert_eq!(strategy.prior_trading_day_close(NaiveDate::from_ymd_opt(2024, 1, 12).unwrap()), None);
+    }
+
+    // Writes minute bars as a loader-compatible CSV
+    fn write_minute_csv(name: &str, bars: &[OhlcBar]) -> std::path::PathBuf {
+        let mut contents = String::from("date,open,high,low,close,volume\n");
+        for bar in bars {
+            contents.push_str(&format!("{},{},{},{},{},{}\n",
+                bar.datetime, bar.open, bar.high, bar.low, bar.close, bar.volume));
+        }
+        let path = temp_path(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_trades_only_run_skips_metrics() {
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        bars.extend(breakout_day("2024-01-16", 95.0));
+        let input = write_minute_csv("fast_input.csv", &bars);
+
+        let mut runs = Vec::new();
+        for compute_metrics in [true, false] {
+            let config = StrategyConfig { compute_metrics, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            let output = temp_path(&format!("fast_output_{}.csv", compute_metrics));
+            let metrics = strategy.run(input.to_str().unwrap(), output.to_str().unwrap()).unwrap();
+            let saved = std::fs::read_to_string(&output).unwrap();
+            std::fs::remove_file(&output).ok();
+            runs.push((metrics, saved));
+        }
+        std::fs::remove_file(&input).ok();
+
+        assert_eq!(runs[0].0.as_ref().unwrap().total_trades, 2);
+        assert!(runs[1].0.is_none());
+        assert_eq!(runs[0].1, runs[1].1);
+    }
+
+    #[test]
+    fn test_require_rearm_before_reentry() {
+        // Bullish 9:25 candle sets the level at 102; the 103 long is stopped at 102.5
+        let bars = vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+            test_bar("2024-01-15 09:35:00", 103.0, 103.2, 102.4, 102.8),
+            // Still closing above the level: continuous hold, not a fresh breakout
+            test_bar("2024-01-15 09:40:00", 102.8, 103.0, 102.6, 102.9),
+            // Pullback inside the level, then a re-break
+            test_bar("2024-01-15 09:45:00", 102.9, 103.0, 101.5, 101.8),
+            test_bar("2024-01-15 09:50:00", 101.8, 103.5, 101.7, 103.2),
+            test_bar("2024-01-15 15:15:00", 105.0, 105.5, 104.5, 105.0),
+        ];
+
+        let mut entries = Vec::new();
+        for require_rearm in [false, true] {
+            let config = StrategyConfig {
+                stop_loss_points: Some(0.5),
+                max_trades_per_day: 3,
+                require_rearm,
+                ..StrategyConfig::default()
+            };
+            let mut strategy = NiftyStrategy::with_config(config);
+            run_pipeline(&mut strategy, bars.clone());
+            entries.push(strategy.trades.iter().map(|t| t.entry_time.time()).collect::<Vec<_>>());
+        }
+
+        let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(entries[0][..2], [t(9, 30), t(9, 40)]);
+        assert_eq!(entries[1], vec![t(9, 30), t(9, 50)]);
+    }
+
+    #[test]
+    fn test_detect_frequency_warns_on_finer_target() {
+        let minute_bars: Vec<OhlcBar> = (0..10)
+            .map(|m| test_bar(&format!("2024-01-15 09:{:02}:00", 15 + m), 100.0, 101.0, 99.0, 100.5))
+            .collect();
+
+        let source = NiftyStrategy::detect_frequency(&minute_bars).unwrap();
+        assert_eq!(source, chrono::Duration::minutes(1));
+
+        let warning = NiftyStrategy::frequency_warning(source, chrono::Duration::seconds(10)).unwrap();
+        assert!(warning.contains("10s is smaller than the source data frequency of 60s"));
+        assert!(NiftyStrategy::frequency_warning(source, chrono::Duration::minutes(5)).is_none());
+    }
+
+    #[test]
+    fn test_open_trade_left_at_end_of_data() {
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        // Final day stops at 11:00, before the 15:15 forced exit
+        bars.extend(breakout_day("2024-01-16", 0.0).into_iter().take(3));
+        bars.push(test_bar("2024-01-16 11:00:00", 103.5, 106.0, 103.0, 105.5));
+
+        let config = StrategyConfig { allow_open_trade_at_end: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars.clone());
+
+        assert_eq!(strategy.trades.len(), 2);
+        assert!(!strategy.trades[0].is_open);
+        let open = &strategy.trades[1];
+        assert!(open.is_open);
+        assert_eq!((open.exit_time, open.exit_price, open.exit_bar_ohlcv), (None, None, None));
+        assert_eq!(open.exit_reason, ExitReason::Open);
+        assert_eq!(open.net_pnl, 0.0);
+        assert_eq!(strategy.calculate_performance_metrics().total_trades, 1);
+
+        // Curves and period tables only walk the closed trade
+        assert_eq!(strategy.percent_return_curve().len(), 2);
+        assert_eq!(strategy.equity_curve().len(), 2);
+        assert_eq!(strategy.monthly_pnl()[&(2024, 1)], strategy.trades[0].net_pnl);
+
+        let path = temp_path("open_trade.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(contents.lines().last().unwrap().ends_with(",,1,,,"));
+
+        let path = temp_path("open_trade.json");
+        strategy.save_results_json(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let raw: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let json_trade = &raw["trades"][1];
+        assert!(json_trade["exit_time"].is_null() && json_trade["exit_price"].is_null());
+        assert_eq!(json_trade["exit_reason"], "Open");
+        assert!(raw["trades"][0]["exit_price"].is_f64());
+
+        // By default the last bar force-closes the position
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, bars);
+        assert!(!strategy.trades[1].is_open);
+        assert_eq!(strategy.trades[1].exit_time.unwrap().time(), NaiveTime::from_hms_opt(11, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_min_body_ratio_skips_wicky_signal_candle() {
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        // Body 0.5 on a 6-point range: mostly wick
+        let mut wicky = breakout_day("2024-01-16", 113.0);
+        wicky[0] = test_bar("2024-01-16 09:25:00", 100.0, 102.0, 96.0, 100.5);
+        bars.extend(wicky);
+
+        let config = StrategyConfig { min_body_ratio: Some(0.25), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        // The strong-bodied day (1 of 3) trades; the wicky day is scored but skipped
+        assert_eq!(strategy.trades.len(), 1);
+        assert_eq!(strategy.trades[0].date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert!((strategy.data[0].candle_body_ratio.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((strategy.data[4].candle_body_ratio.unwrap() - 0.5 / 6.0).abs() < 1e-9);
+        assert_eq!(strategy.data[4].candle_type, None);
+    }
+
+    #[test]
+    fn test_merge_results_interleaves_symbols() {
+        let mut nifty = NiftyStrategy::new();
+        run_pipeline(&mut nifty, [breakout_day("2024-01-15", 113.0), breakout_day("2024-01-17", 100.0)].concat());
+
+        let config = StrategyConfig { symbol: "BANKNIFTY".to_string(), ..StrategyConfig::default() };
+        let mut banknifty = NiftyStrategy::with_config(config);
+        run_pipeline(&mut banknifty, breakout_day("2024-01-16", 108.0));
+
+        let merged = NiftyStrategy::merge_results(&[&nifty, &banknifty]);
+        let symbols: Vec<&str> = merged.trades.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["NIFTY", "BANKNIFTY", "NIFTY"]);
+
+        let combined = nifty.calculate_performance_metrics().total_pnl
+            + banknifty.calculate_performance_metrics().total_pnl;
+        let metrics = merged.calculate_performance_metrics();
+        assert_eq!(metrics.total_trades, 3);
+        assert!((metrics.total_pnl - combined).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_source_count_matches_group_sizes() {
+        let minute_bars = vec![
+            test_bar("2024-01-15 09:15:00", 100.0, 101.0, 99.0, 100.5),
+            test_bar("2024-01-15 09:16:00", 100.5, 101.5, 100.0, 101.0),
+            test_bar("2024-01-15 09:19:00", 101.0, 102.0, 100.5, 101.5),
+            test_bar("2024-01-15 09:20:00", 101.5, 102.0, 101.0, 101.8),
+            test_bar("2024-01-15 09:25:00", 101.8, 102.5, 101.5, 102.0),
+            test_bar("2024-01-15 09:27:00", 102.0, 102.2, 101.0, 101.2),
+        ];
+        let bars = NiftyStrategy::create_bars(minute_bars, 5, VolumeAggregation::Sum);
+
+        let counts: Vec<usize> = bars.iter().map(|b| b.source_count).collect();
+        assert_eq!(counts, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn test_stop_at_signal_candle_extreme() {
+        let config = StrategyConfig { stop_basis: StopBasis::SignalCandleExtreme, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        // Signal candle low is 99; the long from 103 survives 99.5 but not 98.5
+        run_pipeline(&mut strategy, vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+            test_bar("2024-01-15 09:35:00", 103.0, 103.2, 99.5, 100.0),
+            test_bar("2024-01-15 09:40:00", 100.0, 100.5, 98.5, 99.2),
+            test_bar("2024-01-15 15:15:00", 105.0, 105.5, 104.5, 105.0),
+        ]);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(9, 40, 0).unwrap());
+        assert!((trade.exit_price.unwrap() - 99.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bar_close_timestamp_convention() {
+        let config = StrategyConfig {
+            timestamp_convention: TimestampConvention::BarClose,
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:30:00", "2024-01-15 15:15:00", 1, 10.0));
+
+        let path = temp_path("bar_close.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(contents.contains("2024-01-15 09:35:00"));
+        assert!(contents.contains("2024-01-15 15:20:00"));
+        assert!(!contents.contains("09:30:00"));
+    }
+
+    #[test]
+    fn test_filter_diagnostic_when_all_trades_filtered() {
+        let config = StrategyConfig { min_body_ratio: Some(0.9), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, [breakout_day("2024-01-15", 113.0), breakout_day("2024-01-16", 113.0)].concat());
+
+        assert!(strategy.trades.is_empty());
+        assert_eq!(strategy.filter_drops["min_body_ratio"], 2);
+        let diagnostic = strategy.filter_diagnostic().unwrap();
+        assert!(diagnostic.contains("0 signal bars generated"));
+        assert!(diagnostic.contains("2 dropped by min_body_ratio"));
+
+        let mut unfiltered = NiftyStrategy::new();
+        run_pipeline(&mut unfiltered, breakout_day("2024-01-15", 113.0));
+        assert!(unfiltered.filter_diagnostic().is_none());
+    }
+
+    #[test]
+    fn test_config_toml_round_trip() {
+        let config = StrategyConfig {
+            symbol: "BANKNIFTY".to_string(),
+            signal_source: SignalSource::MomentumBreakout,
+            doji_as: DojiPolicy::Skip,
+            min_body_ratio: Some(0.3),
+            stop_loss_points: Some(25.0),
+            stop_basis: StopBasis::SignalCandleExtreme,
+            max_trades_per_day: 2,
+            session_end: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+            output_utc_offset: FixedOffset::east_opt(5 * 3600 + 30 * 60),
+            ..StrategyConfig::default()
+        };
+        let strategy = NiftyStrategy::with_config(config);
+
+        let path = temp_path("config.toml");
+        strategy.save_config(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let loaded = StrategyConfig::load(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(contents.contains("output_utc_offset = \"+05:30\""));
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", strategy.config));
+    }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let path = temp_path("partial.toml");
+        std::fs::write(&path, "symbol = \"FINNIFTY\"\nmax_trades_per_day = 3\n").unwrap();
+        let loaded = StrategyConfig::load(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.symbol, "FINNIFTY");
+        assert_eq!(loaded.max_trades_per_day, 3);
+        assert_eq!(loaded.quantity, 1.0);
+        assert!(loaded.output_utc_offset.is_none());
+    }
+
+    #[test]
+    fn test_session_breaks_excluded_from_holding_time() {
+        let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let config = StrategyConfig {
+            session_start: t(9, 0),
+            session_end: t(15, 0),
+            session_breaks: vec![(t(11, 30), t(12, 30))],
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        let held = strategy.trading_duration(at("2024-01-15 11:00:00"), at("2024-01-15 14:00:00"));
+        assert_eq!(held, chrono::Duration::minutes(120));
+
+        // 5 trading hours per session; a 09:00-14:00 hold spans 4 of them
+        strategy.load_bars(vec![test_bar("2024-01-15 09:00:00", 100.0, 101.0, 99.0, 100.5)]);
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:00:00", "2024-01-15 14:00:00", 1, 5.0));
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.exposure_pct - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_expectancy_bps_normalizes_price_levels() {
+        let mut strategy = NiftyStrategy::new();
+        // +10 on a 100 entry is 1000 bps; -10 on a 1000 entry is -100 bps
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 10.0));
+        let mut high_priced = test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, -10.0);
+        high_priced.entry_price = 1000.0;
+        strategy.trades.push(high_priced);
+
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.expectancy_bps - 450.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sweep_signal_time() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.5, 100.5, 103.0),
+            // Bearish 9:35 candle with its low at 101
+            test_bar("2024-01-15 09:35:00", 103.0, 103.2, 101.0, 101.5),
+            test_bar("2024-01-15 09:40:00", 101.5, 101.8, 100.0, 100.5),
+            test_bar("2024-01-15 15:15:00", 108.0, 108.5, 107.5, 108.0),
+        ]);
+
+        let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let results = strategy.sweep_signal_time(&[t(9, 25), t(9, 35)]).unwrap();
+
+        assert_eq!(results.len(), 2);
+        // Long from 103 vs short from 100.5 into the 108 exit
+        assert!(results[&t(9, 25)].total_pnl > 0.0);
+        assert!(results[&t(9, 35)].total_pnl < 0.0);
+        // The source bars are left untouched
+        assert!(strategy.data.iter().all(|b| b.candle_type.is_none()));
+    }
+
+    #[test]
+    fn test_load_bom_and_padded_fields() {
+        let path = temp_path("bom.csv");
+        let contents = "\u{FEFF}date, open, high, low, close, volume\n\
+            2024-01-15 09:15:00 , 100.0 , 101.0, 99.0 , 100.5 , 1000 \n\
+            2024-01-15 09:16:00, 100.5,101.5 ,100.0, 101.0,  800\n";
+        std::fs::write(&path, contents).unwrap();
+
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_and_prepare_data(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(strategy.data.len(), 1);
+        let bar = &strategy.data[0];
+        assert_eq!(bar.open, 100.0);
+        assert_eq!(bar.high, 101.5);
+        assert_eq!(bar.close, 101.0);
+        assert_eq!(bar.volume, 1800.0);
+    }
+
+    #[test]
+    fn test_profitable_days_pct() {
+        let mut strategy = NiftyStrategy::new();
+        // Day 1 nets +2 over two trades, day 2 nets -1, day 3 nets +4
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 11:00:00", 1, 5.0));
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 11:30:00", "2024-01-15 15:15:00", 1, -3.0));
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, -1.0));
+        strategy.trades.push(test_trade("2024-01-17", "2024-01-17 09:35:00", "2024-01-17 15:15:00", -1, 4.0));
+
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.profitable_days_pct - 200.0 / 3.0).abs() < 1e-9);
+        assert!((metrics.win_rate - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_max_risk_per_trade_skips_wide_stop() {
+        // Day 1 signal candle is 3 points tall, day 2 is 12: stops at the candle extremes
+        let mut wide = breakout_day("2024-01-16", 113.0);
+        wide[0] = test_bar("2024-01-16 09:25:00", 92.0, 102.0, 90.0, 101.0);
+        let bars = [breakout_day("2024-01-15", 113.0), wide].concat();
+
+        let config = StrategyConfig {
+            quantity: 2.0,
+            stop_basis: StopBasis::SignalCandleExtreme,
+            max_risk_per_trade: Some(10.0),
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config.clone());
+        run_pipeline(&mut strategy, bars.clone());
+
+        // 4 points x 2 fits the cap; 13 points x 2 does not
+        assert_eq!(strategy.trades.len(), 1);
+        assert_eq!(strategy.trades[0].date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(strategy.filter_drops["max_risk_per_trade"], 1);
+
+        let config = StrategyConfig { risk_cap_policy: RiskCapPolicy::ReduceSize, ..config };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+        assert_eq!(strategy.trades.len(), 2);
+        assert!((strategy.trades[1].quantity - 10.0 / 13.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_quantity_rounds_to_whole_lots() {
+        let path = temp_path("symbols_lots.csv");
+        std::fs::write(&path, "symbol,tick_size,lot_size,point_value,cost_bps\nNIFTY,0.05,50,1,0\n").unwrap();
+        // Day 1 risks 4 points per unit against the 13 on day 2
+        let mut wide = breakout_day("2024-01-16", 113.0);
+        wide[0] = test_bar("2024-01-16 09:25:00", 92.0, 102.0, 90.0, 101.0);
+        let mut bars = [breakout_day("2024-01-15", 113.0), wide].concat();
+        for bar in &mut bars {
+            bar.volume = 2000.0;
+        }
+        let run = |config: StrategyConfig| {
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.load_symbols(path.to_str().unwrap()).unwrap();
+            run_pipeline(&mut strategy, bars.clone());
+            strategy
+        };
+
+        // 120 units is two lots; a 10% share of 2000 is 200 units, four lots
+        let strategy = run(StrategyConfig { quantity: 120.0, ..StrategyConfig::default() });
+        assert_eq!(strategy.trades[0].quantity, 100.0);
+        let strategy = run(StrategyConfig { quantity: 500.0, max_volume_participation: Some(0.1), ..StrategyConfig::default() });
+        assert_eq!(strategy.trades[0].quantity, 200.0);
+
+        // 1000 / 4 = 250 units is exactly five lots; 1000 / 13 = 76.9 rounds down to one
+        let strategy = run(StrategyConfig {
+            quantity: 500.0,
+            stop_basis: StopBasis::SignalCandleExtreme,
+            max_risk_per_trade: Some(1000.0),
+            risk_cap_policy: RiskCapPolicy::ReduceSize,
+            ..StrategyConfig::default()
+        });
+        assert_eq!(strategy.trades[0].quantity, 250.0);
+        assert_eq!(strategy.trades[1].quantity, 50.0);
+
+        // Less than a lot never trades
+        let strategy = run(StrategyConfig::default());
+        std::fs::remove_file(&path).ok();
+        assert!(strategy.trades.is_empty());
+        assert_eq!(strategy.filter_drops["lot_size"], 2);
+    }
+
+    #[test]
+    fn test_walk_forward_stitches_out_of_sample_curve() {
+        // Winning days except a small loss on the 18th
+        let days = [("2024-01-15", 110.0), ("2024-01-16", 108.0), ("2024-01-17", 106.0),
+                    ("2024-01-18", 102.0), ("2024-01-19", 107.0), ("2024-01-22", 105.0)];
+        let bars: Vec<OhlcBar> = days.iter().flat_map(|(date, exit)| breakout_day(date, *exit)).collect();
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(bars);
+
+        // A signal time with no bars never trades, so the default config wins every window
+        let idle = StrategyConfig { signal_time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(), ..StrategyConfig::default() };
+        let result = strategy.walk_forward(2, 2, &[idle, StrategyConfig::default()]).unwrap();
+
+        assert_eq!(result.windows.len(), 2);
+        assert!(result.windows.iter().all(|w| w.params == 1));
+        assert_eq!(result.windows[1].out_of_sample, (NaiveDate::from_ymd_opt(2024, 1, 19).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 22).unwrap()));
+
+        // Four out-of-sample trades; the second window continues from where the first ended
+        let first = result.windows[0].metrics.total_pnl;
+        let second = result.windows[1].metrics.total_pnl;
+        assert_eq!(result.equity_curve.len(), 5);
+        assert!((result.equity_curve[2] - first).abs() < 1e-9);
+        assert!((result.equity_curve[4] - (first + second)).abs() < 1e-9);
+        assert!(result.equity_curve[3] > first);
+    }
+
+    #[test]
+    fn test_load_csv_without_volume_column() {
+        let path = temp_path("no_volume.csv");
+        let bars = breakout_day("2024-01-15", 110.0);
+        let mut contents = String::from("date,open,high,low,close\n");
+        for bar in &bars {
+            contents.push_str(&format!("{},{},{},{},{}\n", bar.datetime, bar.open, bar.high, bar.low, bar.close));
+        }
+        std::fs::write(&path, contents).unwrap();
+
+        // The volume cap would otherwise reject every fill against zero volume
+        let config = StrategyConfig { max_volume_participation: Some(0.1), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_and_prepare_data(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+        strategy.identify_signal_candles().unwrap();
+        strategy.generate_trading_signals().unwrap();
+        strategy.identify_trades().unwrap();
+
+        assert!(!strategy.has_volume);
+        assert!(strategy.data.iter().all(|bar| bar.volume == 0.0));
+        assert_eq!(strategy.trades.len(), 1);
+        assert_eq!(strategy.trades[0].quantity, 1.0);
+    }
+
+    #[test]
+    fn test_metrics_over_date_range() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades = vec![
+            test_trade("2024-01-15", "2024-01-15 09:30:00", "2024-01-15 15:15:00", 1, 5.0),
+            test_trade("2024-02-15", "2024-02-15 09:30:00", "2024-02-15 15:15:00", 1, -3.0),
+            test_trade("2024-03-15", "2024-03-15 09:30:00", "2024-03-15 15:15:00", 1, 4.0),
+            test_trade("2024-04-15", "2024-04-15 09:30:00", "2024-04-15 15:15:00", 1, 2.0),
+        ];
+        let full = strategy.calculate_performance_metrics();
+        let q1 = strategy.calculate_performance_metrics_range(
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+        );
+
+        assert_eq!(full.total_trades, 4);
+        assert_eq!(q1.total_trades, 3);
+        assert!((full.total_pnl - 8.0).abs() < 1e-9);
+        assert!((q1.total_pnl - 6.0).abs() < 1e-9);
+        assert!((q1.win_rate - 200.0 / 3.0).abs() < 1e-9);
+
+        // Two bar days a month: Q1 holds 3 x 345 of its 6 x 375 session minutes, 46%, rather
+        // than 34.5% against all eight days in the data
+        let bars: Vec<OhlcBar> = ["01-15", "01-16", "02-15", "02-16", "03-15", "03-16", "04-15", "04-16"].iter()
+            .map(|day| test_bar(&format!("2024-{} 09:15:00", day), 100.0, 101.0, 99.0, 100.5))
+            .collect();
+        let trades = std::mem::take(&mut strategy.trades);
+        strategy.load_bars(bars);
+        strategy.trades = trades;
+        let q1 = strategy.calculate_performance_metrics_range(
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+        );
+        assert!((q1.exposure_pct - 46.0).abs() < 1e-9);
+        let april = strategy.calculate_performance_metrics_range(
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(),
+        );
+        assert!((april.exposure_pct - 46.0).abs() < 1e-9);
+        assert!((strategy.calculate_performance_metrics().exposure_pct - 46.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_retest_entry_fills_at_level() {
+        let config = StrategyConfig { entry_mode: EntryMode::Retest, ..StrategyConfig::default() };
+
+        // The 9:35 bar dips back to the 102 signal high after the 9:30 breakout
+        let mut bars = breakout_day("2024-01-15", 110.0);
+        bars[2] = test_bar("2024-01-15 09:35:00", 103.0, 104.0, 101.5, 103.5);
+        let mut strategy = NiftyStrategy::with_config(config.clone());
+        run_pipeline(&mut strategy, bars);
+        assert_eq!(strategy.trades.len(), 1);
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.entry_price, 102.0);
+        assert_eq!(trade.entry_time, NaiveDateTime::parse_from_str("2024-01-15 09:35:00", "%Y-%m-%d %H:%M:%S").unwrap());
+
+        // Price never returns to the level before the exit bar
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, breakout_day("2024-01-15", 110.0));
+        assert!(strategy.trades.is_empty());
+    }
+
+    #[test]
+    fn test_data_health_report() {
+        let path = temp_path("health.csv");
+        let contents = "date,open,high,low,close,volume\n\
+            2024-01-15 09:15:00,100,101,99,100.5,1000\n\
+            2024-01-15 09:20:00,100.5,102,100,101,1000\n\
+            2024-01-15 09:35:00,101,103,100.5,102,1000\n\
+            not a timestamp,101,103,100.5,102,1000\n\
+            2024-01-16 09:15:00,102,104,98,103,1000\n";
+        std::fs::write(&path, contents).unwrap();
+
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_and_prepare_data(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+        let report = strategy.data_health_report().unwrap();
+
+        assert_eq!(report.first_date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(report.last_date, NaiveDate::from_ymd_opt(2024, 1, 16).unwrap());
+        assert_eq!(report.trading_days, 2);
+        assert!((report.avg_bars_per_day - 2.0).abs() < 1e-9);
+        assert_eq!((report.min_price, report.max_price), (98.0, 104.0));
+        assert_eq!(report.rejected_rows, 1);
+        assert_eq!(report.gaps, 1);
+    }
+
+    #[test]
+    fn test_sort_trades_by_entry_time() {
+        let trades = vec![
+            test_trade("2024-01-16", "2024-01-16 09:30:00", "2024-01-16 15:15:00", 1, 1.0),
+            test_trade("2024-01-15", "2024-01-15 11:00:00", "2024-01-15 15:15:00", 1, 2.0),
+            test_trade("2024-01-15", "2024-01-15 09:45:00", "2024-01-15 15:15:00", -1, 3.0),
+        ];
+        let mut by_date = NiftyStrategy::new();
+        by_date.trades = trades.clone();
+        by_date.sort_trades();
+        let config = StrategyConfig { sort_by_entry_time: true, ..StrategyConfig::default() };
+        let mut by_entry = NiftyStrategy::with_config(config);
+        by_entry.trades = trades;
+        by_entry.sort_trades();
+
+        let pnls = |s: &NiftyStrategy| s.trades.iter().map(|t| t.net_pnl).collect::<Vec<_>>();
+        assert_eq!(pnls(&by_date), vec![2.0, 3.0, 1.0]);
+        assert_eq!(pnls(&by_entry), vec![3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_mar_ratio_over_one_year() {
+        let mut strategy = NiftyStrategy::new();
+        // +10%, -10% and +20% compound to 1.188 over 366 days, with a 10% drawdown
+        strategy.trades = vec![
+            test_trade("2023-01-02", "2023-01-02 09:30:00", "2023-01-02 15:15:00", 1, 10.0),
+            test_trade("2023-06-01", "2023-06-01 09:30:00", "2023-06-01 15:15:00", 1, -10.0),
+            test_trade("2024-01-03", "2024-01-03 09:30:00", "2024-01-03 15:15:00", 1, 20.0),
+        ];
+        let metrics = strategy.calculate_performance_metrics();
+
+        let expected_cagr = (1.188_f64.powf(365.25 / 366.0) - 1.0) * 100.0;
+        assert!((metrics.cagr_pct - expected_cagr).abs() < 1e-9);
+        assert!((metrics.mar_ratio - expected_cagr / 10.0).abs() < 1e-9);
+
+        // A trailing half-year window is too short to annualize
+        strategy.config.mar_period_years = Some(0.5);
+        let metrics = strategy.calculate_performance_metrics();
+        assert_eq!((metrics.cagr_pct, metrics.mar_ratio), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_dead_trade_exits_without_progress() {
+        // Entry at 103 on the 9:30 close, then the trade goes nowhere for half an hour
+        let mut bars = breakout_day("2024-01-15", 110.0);
+        bars.insert(3, test_bar("2024-01-15 10:00:00", 103.0, 103.8, 102.6, 103.2));
+        let config = StrategyConfig {
+            dead_trade_minutes: Some(30),
+            min_progress_points: 2.0,
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars.clone());
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::NoProgress);
+        assert_eq!(trade.exit_price, Some(103.2));
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+
+        // Without the rule the trade rides to the 15:15 exit
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, bars);
+        assert_eq!(strategy.trades[0].exit_reason, ExitReason::EndOfDay);
+    }
+
+    #[test]
+    fn test_running_session_high_low() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars([breakout_day("2024-01-15", 110.0), breakout_day("2024-01-16", 95.0)].concat());
+
+        for day in strategy.data.chunk_by(|a, b| a.date == b.date) {
+            assert!(day.windows(2).all(|pair| pair[1].session_high >= pair[0].session_high));
+            assert!(day.windows(2).all(|pair| pair[1].session_low <= pair[0].session_low));
+            // Each session starts from its own first bar
+            assert_eq!((day[0].session_high, day[0].session_low), (102.0, 99.0));
+        }
+        let highs: Vec<f64> = strategy.data[..4].iter().map(|bar| bar.session_high).collect();
+        assert_eq!(highs, vec![102.0, 103.5, 104.0, 111.0]);
+        assert_eq!(strategy.data[7].session_low, 94.0);
+    }
+
+    #[test]
+    fn test_format_money_grouping() {
+        let mut config = StrategyConfig::default();
+        assert_eq!(format_money(123456.784, &config), "123456.78");
+
+        config.currency_symbol = "₹".to_string();
+        config.digit_grouping = DigitGrouping::Indian;
+        assert_eq!(format_money(123456.784, &config), "₹1,23,456.78");
+        assert_eq!(format_money(-12345678.9, &config), "-₹1,23,45,678.90");
+
+        config.currency_symbol = "$".to_string();
+        config.digit_grouping = DigitGrouping::Standard;
+        assert_eq!(format_money(1234567.5, &config), "$1,234,567.50");
+        assert_eq!(format_money(999.999, &config), "$1,000.00");
+    }
+
+    #[test]
+    fn test_skip_boundary_days() {
+        let bars = [
+            breakout_day("2024-01-15", 110.0),
+            breakout_day("2024-01-16", 110.0),
+            breakout_day("2024-01-17", 110.0),
+            breakout_day("2024-01-18", 110.0),
+        ].concat();
+        let config = StrategyConfig { skip_first_n_days: 1, skip_last_n_days: 2, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let dates: Vec<NaiveDate> = strategy.trades.iter().map(|t| t.date).collect();
+        assert_eq!(dates, vec![NaiveDate::from_ymd_opt(2024, 1, 16).unwrap()]);
+    }
+
+    #[test]
+    fn test_stop_takes_precedence_over_forced_exit() {
+        // The 15:15 exit bar opens at 98, through the 99 signal-candle-low stop
+        let bars = breakout_day("2024-01-15", 98.0);
+        let config = StrategyConfig { stop_basis: StopBasis::SignalCandleExtreme, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars.clone());
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.exit_price, Some(98.0));
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(15, 15, 0).unwrap());
+
+        // Same fill without a stop is an ordinary end-of-day exit
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, bars);
+        assert_eq!(strategy.trades[0].exit_reason, ExitReason::EndOfDay);
+        assert_eq!(strategy.trades[0].exit_price, Some(98.0));
+    }
+
+    #[test]
+    fn test_load_gzipped_csv_matches_plain() {
+        let bars: Vec<OhlcBar> = (0..12)
+            .map(|minute| {
+                let price = 100.0 + minute as f64;
+                test_bar(&format!("2024-01-15 09:{:02}:00", 15 + minute), price, price + 1.0, price - 1.0, price + 0.5)
+            })
+            .collect();
+        let plain_path = write_minute_csv("plain.csv", &bars);
+        let gz_path = temp_path("minute.csv.gz");
+        let mut encoder = GzEncoder::new(File::create(&gz_path).unwrap(), Compression::default());
+        encoder.write_all(&std::fs::read(&plain_path).unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        let mut plain = NiftyStrategy::new();
+        plain.load_and_prepare_data(plain_path.to_str().unwrap()).unwrap();
+        let mut gzipped = NiftyStrategy::new();
+        gzipped.load_and_prepare_data(gz_path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&plain_path).ok();
+        std::fs::remove_file(&gz_path).ok();
+
+        assert_eq!(plain.data.len(), 3);
+        assert_eq!(gzipped.data.len(), plain.data.len());
+        let source_rows = |s: &NiftyStrategy| s.data.iter().map(|bar| bar.source_count).sum::<usize>();
+        assert_eq!(source_rows(&gzipped), 12);
+        for (a, b) in gzipped.data.iter().zip(&plain.data) {
+            assert_eq!((a.datetime, a.open, a.high, a.low, a.close), (b.datetime, b.open, b.high, b.low, b.close));
+        }
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let path = temp_path("broker.csv");
+        let contents = "timestamp,o,h,l,c,vol,oi\n\
+            2024-01-15 09:15:00,100,101,99,100.5,1000,50\n\
+            2024-01-15 09:16:00,100.5,102,100,101,800,55\n";
+        std::fs::write(&path, contents).unwrap();
+
+        let columns = ColumnMapping {
+            date: "timestamp".to_string(),
+            open: "o".to_string(),
+            high: "h".to_string(),
+            low: "l".to_string(),
+            close: "c".to_string(),
+            volume: "vol".to_string(),
+        };
+        let config = StrategyConfig { columns: columns.clone(), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_and_prepare_data(path.to_str().unwrap()).unwrap();
+        assert_eq!(strategy.data.len(), 1);
+        let bar = &strategy.data[0];
+        assert_eq!((bar.open, bar.high, bar.low, bar.close, bar.volume), (100.0, 102.0, 99.0, 101.0, 1800.0));
+
+        // A mapped column absent from the header is reported by name
+        let config = StrategyConfig { columns: ColumnMapping { close: "last".to_string(), ..columns }, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        let err = strategy.load_and_prepare_data(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains("'last' column required for the close field"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_from_bytes_matches_file() {
+        let path = write_minute_csv("bytes.csv", &[breakout_day("2024-01-15", 110.0), breakout_day("2024-01-16", 96.0)].concat());
+        let bytes = std::fs::read(&path).unwrap();
+        let mut from_file = NiftyStrategy::new();
+        from_file.load_and_prepare_data(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let mut from_bytes = NiftyStrategy::new();
+        from_bytes.load_from_bytes(&bytes).unwrap();
+
+        assert_eq!(from_bytes.data.len(), 8);
+        assert_eq!(from_bytes.data.len(), from_file.data.len());
+        for (a, b) in from_bytes.data.iter().zip(&from_file.data) {
+            assert_eq!((a.datetime, a.open, a.high, a.low, a.close, a.volume), (b.datetime, b.open, b.high, b.low, b.close, b.volume));
+        }
+    }
+
+    #[test]
+    fn test_price_scale_converts_integer_ticks() {
+        let contents = "date,open,high,low,close,volume\n\
+            2024-01-15 09:15:00,19500,19550,19480,19525,1000\n";
+        let config = StrategyConfig { price_scale: 0.01, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(contents.as_bytes()).unwrap();
+
+        let bar = &strategy.data[0];
+        assert!((bar.open - 195.0).abs() < 1e-9);
+        assert!((bar.high - 195.5).abs() < 1e-9);
+        assert!((bar.low - 194.8).abs() < 1e-9);
+        assert!((bar.close - 195.25).abs() < 1e-9);
+        assert_eq!(bar.volume, 1000.0);
+    }
+
+    #[test]
+    fn test_bars_held_by_exit_reason() {
+        // Day 1 is stopped on the 9:35 bar; day 2 reaches the 108 target on the 11:00 bar
+        let mut stopped = breakout_day("2024-01-15", 110.0);
+        stopped[2] = test_bar("2024-01-15 09:35:00", 103.0, 103.5, 98.0, 98.5);
+        let mut target = breakout_day("2024-01-16", 110.0);
+        target.insert(3, test_bar("2024-01-16 10:00:00", 103.5, 105.0, 103.0, 104.5));
+        target.insert(4, test_bar("2024-01-16 11:00:00", 104.5, 108.5, 104.0, 108.0));
+        let config = StrategyConfig {
+            stop_basis: StopBasis::SignalCandleExtreme,
+            take_profit_points: Some(5.0),
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, [stopped, target].concat());
+
+        let breakdown = strategy.metrics_by_exit_reason();
+        assert_eq!(breakdown.len(), 2);
+        let stop = &breakdown[&ExitReason::StopLoss];
+        assert_eq!(stop.trades, 1);
+        assert!((stop.avg_bars_held - 1.0).abs() < 1e-9);
+        assert!(stop.total_pnl < 0.0);
+        let take_profit = &breakdown[&ExitReason::TakeProfit];
+        assert_eq!(take_profit.trades, 1);
+        assert!((take_profit.avg_bars_held - 3.0).abs() < 1e-9);
+        assert!(take_profit.total_pnl > 0.0);
+    }
+
+    #[test]
+    fn test_win_rate_basis_with_scratch_trade() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades = vec![
+            test_trade("2024-01-15", "2024-01-15 09:30:00", "2024-01-15 15:15:00", 1, 5.0),
+            test_trade("2024-01-16", "2024-01-16 09:30:00", "2024-01-16 15:15:00", 1, -2.0),
+            test_trade("2024-01-17", "2024-01-17 09:30:00", "2024-01-17 15:15:00", 1, 0.0),
+        ];
+        assert!((strategy.calculate_performance_metrics().win_rate - 100.0 / 3.0).abs() < 1e-9);
+
+        strategy.config.win_rate_basis = WinRateBasis::NonScratchTrades;
+        assert!((strategy.calculate_performance_metrics().win_rate - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_daily_and_weekly_bars() {
+        // Monday 15th is a holiday; the second week is cut short by the end of the data
+        let bars = [
+            breakout_day("2024-01-16", 110.0),
+            breakout_day("2024-01-17", 95.0),
+            breakout_day("2024-01-19", 104.0),
+            breakout_day("2024-01-22", 120.0),
+        ].concat();
+
+        let daily = NiftyStrategy::create_period_bars(bars.clone(), BarPeriod::Daily);
+        assert_eq!(daily.len(), 4);
+        let day = &daily[1];
+        assert_eq!(day.datetime, NaiveDateTime::parse_from_str("2024-01-17 09:25:00", "%Y-%m-%d %H:%M:%S").unwrap());
+        assert_eq!((day.open, day.high, day.low, day.close), (100.0, 104.0, 94.0, 95.0));
+        assert_eq!(day.volume, 4000.0);
+
+        let weekly = NiftyStrategy::create_period_bars(bars, BarPeriod::Weekly);
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].date, NaiveDate::from_ymd_opt(2024, 1, 16).unwrap());
+        assert_eq!((weekly[0].open, weekly[0].high, weekly[0].low, weekly[0].close), (100.0, 111.0, 94.0, 104.0));
+        assert_eq!(weekly[0].source_count, 12);
+        assert_eq!((weekly[1].date, weekly[1].close), (NaiveDate::from_ymd_opt(2024, 1, 22).unwrap(), 120.0));
+    }
+
+    #[test]
+    fn test_require_candle_flip() {
+        // Bearish 9:25 candle on the 17th, then bullish on the 18th
+        let mut bearish = breakout_day("2024-01-17", 110.0);
+        bearish[0] = test_bar("2024-01-17 09:25:00", 101.0, 102.0, 99.0, 100.0);
+        let bars = [
+            breakout_day("2024-01-15", 110.0),
+            breakout_day("2024-01-16", 110.0),
+            bearish,
+            breakout_day("2024-01-18", 110.0),
+        ].concat();
+        let config = StrategyConfig { require_candle_flip: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        // Back-to-back bullish days don't trade; the bearish day has no downside break
+        let dates: Vec<NaiveDate> = strategy.trades.iter().map(|t| t.date).collect();
+        assert_eq!(dates, vec![NaiveDate::from_ymd_opt(2024, 1, 18).unwrap()]);
+    }
+
+    #[test]
+    fn test_aggregate_high_with_negative_prices() {
+        // Spread instruments can trade below zero
+        let bars = vec![
+            test_bar("2024-01-15 09:15:00", -5.0, -4.0, -6.0, -5.5),
+            test_bar("2024-01-15 09:16:00", -5.5, -3.5, -7.0, -6.0),
+        ];
+        let aggregated = NiftyStrategy::aggregate_bars(&bars, bars[0].datetime, VolumeAggregation::Sum);
+        assert_eq!(aggregated.high, -3.5);
+        assert_eq!(aggregated.low, -7.0);
+    }
+
+    #[test]
+    fn test_save_results_to_stdout_creates_no_file() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 12.5));
+        strategy.save_results("-").unwrap();
+        assert!(!std::path::Path::new("-").exists());
+    }
+
+    #[test]
+    fn test_equity_curve_ends_at_total_pnl() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades = vec![
+            test_trade("2024-01-15", "2024-01-15 09:30:00", "2024-01-15 15:15:00", 1, 5.0),
+            test_trade("2024-01-16", "2024-01-16 09:30:00", "2024-01-16 15:15:00", -1, -2.5),
+            test_trade("2024-01-17", "2024-01-17 09:30:00", "2024-01-17 15:15:00", 1, 4.0),
+        ];
+        let curve = strategy.equity_curve();
+        let metrics = strategy.calculate_performance_metrics();
+
+        assert_eq!(curve.len(), 4);
+        assert_eq!(curve[0], (strategy.trades[0].entry_time, 0.0));
+        assert!(curve.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!((curve[3].1 - metrics.total_pnl).abs() < 1e-9);
+
+        let path = temp_path("equity.csv");
+        strategy.save_equity_curve(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(contents.lines().count(), 5);
+        assert_eq!(contents.lines().last().unwrap(), "2024-01-17 15:15:00,6.5000");
+    }
+
+    #[test]
+    fn test_sortino_ratio() {
+        let mut strategy = NiftyStrategy::new();
+        // Mean PnL 2.0; losses -2 and -4 have a standard deviation of 1
+        for (day, pnl) in [(15, 6.0), (16, -2.0), (17, 8.0), (18, -4.0)] {
+            let date = format!("2024-01-{}", day);
+            strategy.trades.push(test_trade(&date, &format!("{} 09:30:00", date), &format!("{} 15:15:00", date), 1, pnl));
+        }
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.sortino_ratio - 2.0).abs() < 1e-9);
+
+        // No losing trades means no downside deviation
+        strategy.trades.retain(|t| t.net_pnl > 0.0);
+        assert_eq!(strategy.calculate_performance_metrics().sortino_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_half_populated_signal_candle_is_an_error() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_bars(breakout_day("2024-01-15", 110.0));
+        strategy.identify_signal_candles().unwrap();
+        strategy.data[1].candle_val = None;
+
+        let err = strategy.generate_trading_signals().unwrap_err();
+        assert!(err.to_string().contains("half-populated signal candle"), "{}", err);
+    }
+
+    #[test]
+    fn test_day_reset_time_assigns_trading_day() {
+        let contents = "date,open,high,low,close,volume\n\
+            2024-01-15 16:55:00,100,101,99,100.5,10\n\
+            2024-01-15 17:00:00,100.5,103,100,102,10\n\
+            2024-01-16 09:15:00,102,102.5,98,99,10\n";
+        let config = StrategyConfig { day_reset_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(contents.as_bytes()).unwrap();
+
+        let dates: Vec<u32> = strategy.data.iter().map(|bar| bar.date.day()).collect();
+        assert_eq!(dates, vec![15, 16, 16]);
+        // The session high carries over the evening bar into the next morning
+        assert_eq!(strategy.data[1].session_high, 103.0);
+        assert_eq!((strategy.data[2].session_high, strategy.data[2].session_low), (103.0, 98.0));
+
+        let mut calendar = NiftyStrategy::new();
+        calendar.load_from_bytes(contents.as_bytes()).unwrap();
+        assert_eq!(calendar.data[1].date.day(), 15);
+    }
+
+    #[test]
+    fn test_max_drawdown_halt() {
+        // Losing days of about 8 and 5 points, then two winners
+        let days = [("2024-01-15", 95.0), ("2024-01-16", 98.0), ("2024-01-17", 110.0), ("2024-01-18", 110.0)];
+        let bars: Vec<OhlcBar> = days.iter().flat_map(|(date, exit)| breakout_day(date, *exit)).collect();
+        let config = StrategyConfig { max_drawdown_halt: Some(10.0), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config.clone());
+        run_pipeline(&mut strategy, bars.clone());
+
+        // The second loss breaches the limit and no further trades are opened
+        assert_eq!(strategy.trades.len(), 2);
+        assert_eq!(strategy.filter_drops["max_drawdown_halt"], 2);
+
+        // A paper winner on the 17th brings the drawdown back within the resume level
+        let config = StrategyConfig { drawdown_resume: Some(10.0), ..config };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+        let dates: Vec<u32> = strategy.trades.iter().map(|t| t.date.day()).collect();
+        assert_eq!(dates, vec![15, 16, 18]);
+    }
+
+    #[test]
+    fn test_consecutive_win_loss_streaks() {
+        let mut strategy = NiftyStrategy::new();
+        // W W L L L 0 L W W W L
+        let pnls = [3.0, 1.0, -2.0, -1.0, -4.0, 0.0, -1.0, 2.0, 5.0, 1.0, -3.0];
+        for (i, pnl) in pnls.iter().enumerate() {
+            let date = format!("2024-02-{:02}", i + 1);
+            strategy.trades.push(test_trade(&date, &format!("{} 09:30:00", date), &format!("{} 15:15:00", date), 1, *pnl));
+        }
+        let metrics = strategy.calculate_performance_metrics();
+        assert_eq!(metrics.max_consecutive_wins, 3);
+        assert_eq!(metrics.max_consecutive_losses, 3);
+
+        // Without the scratch trade the losing streak runs to four
+        strategy.trades.remove(5);
+        assert_eq!(strategy.calculate_performance_metrics().max_consecutive_losses, 4);
+    }
+
+    #[test]
+    fn test_short_stop_loss_points_recorded_in_results() {
+        // Bearish 9:25 candle (low 99) broken by the 9:30 close at 98; the 9:40 bar spikes to 101
+        let bars = vec![
+            test_bar("2024-01-15 09:25:00", 101.0, 102.0, 99.0, 100.0),
+            test_bar("2024-01-15 09:30:00", 100.0, 100.5, 97.5, 98.0),
+            test_bar("2024-01-15 09:35:00", 98.0, 99.0, 97.0, 98.5),
+            test_bar("2024-01-15 09:40:00", 98.5, 101.0, 98.0, 100.5),
+            test_bar("2024-01-15 15:15:00", 96.0, 97.0, 95.0, 96.0),
+        ];
+        let config = StrategyConfig { stop_loss_points: Some(2.5), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.signal, -1);
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.exit_price, Some(100.5));
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(9, 40, 0).unwrap());
+
+        let path = temp_path("stop_results.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(contents.lines().next().unwrap().ends_with(",exit_reason"));
+        assert!(contents.lines().nth(1).unwrap().ends_with(",StopLoss"));
+    }
+
+    #[test]
+    fn test_rolling_correlation_first_full_window() {
+        let mut strategy = NiftyStrategy::new();
+        for (day, pnl) in [(15, 1.0), (16, 2.0), (17, 3.0)] {
+            let date = format!("2024-01-{}", day);
+            strategy.trades.push(test_trade(&date, &format!("{} 09:30:00", date), &format!("{} 15:15:00", date), 1, pnl));
+        }
+        let benchmark: BTreeMap<NaiveDate, f64> = [(15, 2.0), (16, 4.0), (17, 7.0), (18, 1.0)]
+            .into_iter()
+            .map(|(day, ret)| (NaiveDate::from_ymd_opt(2024, 1, day).unwrap(), ret))
+            .collect();
+
+        let rolling = strategy.rolling_correlation(&benchmark, 3);
+        assert_eq!(rolling.len(), 2);
+        assert_eq!(rolling[0].0, NaiveDate::from_ymd_opt(2024, 1, 17).unwrap());
+        // x = [1, 2, 3], y = [2, 4, 7]: covariance sum 5, squared deviations 2 and 114/9
+        assert!((rolling[0].1 - 5.0 / (2.0 * 114.0 / 9.0_f64).sqrt()).abs() < 1e-9);
+        assert_eq!(rolling[1].0, NaiveDate::from_ymd_opt(2024, 1, 18).unwrap());
+    }
+
+    #[test]
+    fn test_r_based_trailing_locks_profit() {
+        // Entry 103 with a 2-point stop, so 1R = 2 points. The 9:35 bar runs to 107.5 (+2.25R)
+        // and the 9:40 bar pulls back through 105 (+1R).
+        let mut bars = breakout_day("2024-01-15", 110.0);
+        bars[2] = test_bar("2024-01-15 09:35:00", 103.0, 107.5, 102.5, 107.0);
+        bars.insert(3, test_bar("2024-01-15 09:40:00", 107.0, 107.2, 104.5, 104.8));
+        let config = StrategyConfig {
+            stop_loss_points: Some(2.0),
+            r_based_trailing: vec![(1.0, 0.0), (2.0, 1.0)],
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::StopLoss);
+        assert_eq!(trade.exit_price, Some(105.0));
+        assert_eq!(trade.exit_time.unwrap().time(), NaiveTime::from_hms_opt(9, 40, 0).unwrap());
+    }
+
+    #[test]
+    fn test_r_based_trailing_waits_for_activation_delay() {
+        // Same +2.25R spike at 9:35, but it falls inside the 15-minute delay window, so the
+        // 9:45 dip to 104.5 must not hit a locked-in 105 stop
+        let mut bars = breakout_day("2024-01-15", 110.0);
+        bars[2] = test_bar("2024-01-15 09:35:00", 103.0, 107.5, 102.5, 107.0);
+        bars.insert(3, test_bar("2024-01-15 09:45:00", 107.0, 107.2, 104.5, 104.8));
+        let config = StrategyConfig {
+            stop_loss_points: Some(2.0),
+            stop_activation_delay_minutes: 15,
+            r_based_trailing: vec![(1.0, 0.0), (2.0, 1.0)],
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::EndOfDay);
+        assert_eq!(trade.exit_price, Some(110.0));
+    }
+
+    #[test]
+    fn test_take_profit_target() {
+        // Entry at the 9:30 close of 103: stop 101, target 106
+        let config = StrategyConfig {
+            stop_loss_points: Some(2.0),
+            take_profit_points: Some(3.0),
+            ..StrategyConfig::default()
+        };
+        let run_day = |config: &StrategyConfig, probe: OhlcBar| {
+            let mut bars = breakout_day("2024-01-15", 104.0);
+            bars.insert(3, probe);
+            let mut strategy = NiftyStrategy::with_config(config.clone());
+            run_pipeline(&mut strategy, bars);
+            let trade = &strategy.trades[0];
+            (trade.exit_reason, trade.exit_price.unwrap(), trade.exit_time.unwrap().time())
+        };
+        let ten = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+
+        // Target hit fills at the target, not the bar close
+        let hit = run_day(&config, test_bar("2024-01-15 10:00:00", 104.0, 106.5, 103.5, 104.0));
+        assert_eq!(hit, (ExitReason::TakeProfit, 106.0, ten));
+        // Stop hit
+        let hit = run_day(&config, test_bar("2024-01-15 10:00:00", 103.0, 103.5, 100.5, 101.5));
+        assert_eq!(hit, (ExitReason::StopLoss, 101.0, ten));
+        // Neither: held to the 15:15 open
+        let hit = run_day(&config, test_bar("2024-01-15 10:00:00", 103.0, 105.0, 102.0, 104.0));
+        assert_eq!(hit.0, ExitReason::EndOfDay);
+        assert_eq!(hit.1, 104.0);
+
+        // A bar through both assumes the stop filled first unless configured otherwise
+        let both = test_bar("2024-01-15 10:00:00", 103.0, 107.0, 100.0, 104.0);
+        assert_eq!(run_day(&config, both.clone()), (ExitReason::StopLoss, 101.0, ten));
+        let config = StrategyConfig { exit_precedence: ExitPrecedence::TargetFirst, ..config };
+        assert_eq!(run_day(&config, both), (ExitReason::TakeProfit, 106.0, ten));
+    }
+
+    #[test]
+    fn test_session_adverse_excursion() {
+        // Day 1 dips to 100 after the 103 entry; day 2 only to the 9:35 low of 102.5
+        let mut swing = breakout_day("2024-01-15", 110.0);
+        swing.insert(3, test_bar("2024-01-15 10:00:00", 103.0, 103.5, 100.0, 102.0));
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, [swing, breakout_day("2024-01-16", 110.0)].concat());
+
+        let mae = strategy.session_adverse_excursion().unwrap();
+        assert_eq!(mae.by_day[&NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()], 3.0);
+        assert_eq!(mae.by_day[&NaiveDate::from_ymd_opt(2024, 1, 16).unwrap()], 0.5);
+        assert_eq!(mae.worst, 3.0);
+        assert!((mae.average - 1.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_three_signals_three_trades() {
+        // Three breakouts above 102, each stopped out one point later before the next fires
+        let bars = vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 102.0, 99.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.2, 100.5, 103.0),
+            test_bar("2024-01-15 09:35:00", 103.0, 103.1, 101.5, 101.8),
+            test_bar("2024-01-15 09:40:00", 101.8, 103.8, 101.7, 103.5),
+            test_bar("2024-01-15 09:45:00", 103.5, 103.6, 102.0, 102.2),
+            test_bar("2024-01-15 09:50:00", 102.2, 104.2, 102.1, 104.0),
+            test_bar("2024-01-15 15:15:00", 106.0, 106.5, 105.5, 106.0),
+        ];
+        let config = StrategyConfig { max_trades_per_day: 3, stop_loss_points: Some(1.0), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config.clone());
+        run_pipeline(&mut strategy, bars.clone());
+
+        let entries: Vec<f64> = strategy.trades.iter().map(|t| t.entry_price).collect();
+        assert_eq!(entries, vec![103.0, 103.5, 104.0]);
+        let reasons: Vec<ExitReason> = strategy.trades.iter().map(|t| t.exit_reason).collect();
+        assert_eq!(reasons, vec![ExitReason::StopLoss, ExitReason::StopLoss, ExitReason::EndOfDay]);
+        // No entry opens before the previous position has exited
+        assert!(strategy.trades.windows(2).all(|pair| pair[1].entry_time > pair[0].exit_time.unwrap()));
+
+        let config = StrategyConfig { max_trades_per_day: 2, ..config };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+        assert_eq!(strategy.trades.len(), 2);
+    }
+
+    #[test]
+    fn test_skip_aggregation_passes_bars_through() {
+        // Five-minute bars stamped two minutes past the bucket boundary
+        let csv = "date,open,high,low,close,volume\n\
+                   2024-01-15 09:17:00,100,102,99,101,500\n\
+                   2024-01-15 09:22:00,101,103,100,102,600\n\
+                   2024-01-15 09:27:00,102,104,101,103,700\n";
+
+        let config = StrategyConfig { skip_aggregation: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+        let rows: Vec<(String, f64, f64, f64, f64, f64)> = strategy.data.iter()
+            .map(|bar| (bar.datetime.to_string(), bar.open, bar.high, bar.low, bar.close, bar.volume))
+            .collect();
+        assert_eq!(rows, vec![
+            ("2024-01-15 09:17:00".to_string(), 100.0, 102.0, 99.0, 101.0, 500.0),
+            ("2024-01-15 09:22:00".to_string(), 101.0, 103.0, 100.0, 102.0, 600.0),
+            ("2024-01-15 09:27:00".to_string(), 102.0, 104.0, 101.0, 103.0, 700.0),
+        ]);
+
+        // Aggregating the same rows re-buckets them onto the five-minute boundaries
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+        let times: Vec<String> = strategy.data.iter().map(|bar| bar.time.to_string()).collect();
+        assert_eq!(times, vec!["09:15:00", "09:20:00", "09:25:00"]);
+    }
+
+    #[test]
+    fn test_flat_signal_candle_is_doji_above_threshold() {
+        let bars = vec![
+            test_bar("2024-01-15 09:25:00", 100.0, 100.0, 100.0, 100.0),
+            test_bar("2024-01-15 09:30:00", 100.0, 101.0, 99.0, 99.5),
+            test_bar("2024-01-15 09:35:00", 99.5, 102.0, 99.5, 101.5),
+        ];
+
+        // Default threshold keeps the doji_as classification, so the flat candle arms shorts
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, bars.clone());
+        assert_eq!(strategy.data[0].candle_type.as_deref(), Some("bearish"));
+        assert_eq!(strategy.data[1].signal, -1);
+
+        let config = StrategyConfig { doji_body_threshold: 0.05, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+        assert!(strategy.data.iter().all(|b| b.candle_type.as_deref() == Some("doji")));
+        assert!(strategy.data.iter().all(|b| b.signal == 0));
+        assert!(strategy.trades.is_empty());
+    }
+
+    #[test]
+    fn test_metrics_by_tag() {
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        bars[1].volume = 5000.0;
+        bars.extend(breakout_day("2024-01-16", 108.0));
+        bars.extend(breakout_day("2024-01-17", 95.0));
+
+        let mut strategy = NiftyStrategy::new();
+        strategy.tagger = Some(Box::new(|_trade, entry_bar| {
+            Some(if entry_bar.volume > 2000.0 { "high-vol-morning" } else { "quiet" }.to_string())
+        }));
+        run_pipeline(&mut strategy, bars);
+
+        let tags: Vec<Option<&str>> = strategy.trades.iter().map(|t| t.tag.as_deref()).collect();
+        assert_eq!(tags, vec![Some("high-vol-morning"), Some("quiet"), Some("quiet")]);
+
+        let by_tag = strategy.metrics_by_tag();
+        assert_eq!(by_tag.keys().map(String::as_str).collect::<Vec<_>>(), vec!["high-vol-morning", "quiet"]);
+        let high_vol = &by_tag["high-vol-morning"];
+        assert_eq!(high_vol.total_trades, 1);
+        assert!((high_vol.total_pnl - strategy.trades[0].net_pnl).abs() < 1e-9);
+        // Held 9:30 to 15:15 on the only day the tag traded, not diluted by the other two
+        assert!((high_vol.exposure_pct - 345.0 / 375.0 * 100.0).abs() < 1e-9);
+        let quiet = &by_tag["quiet"];
+        assert_eq!(quiet.total_trades, 2);
+        assert!((quiet.total_pnl - (strategy.trades[1].net_pnl + strategy.trades[2].net_pnl)).abs() < 1e-9);
+        assert!(quiet.win_rate > 0.0 && quiet.win_rate < high_vol.win_rate);
+
+        // A position still open at the end of the data has no realized PnL to group
+        strategy.trades[2].is_open = true;
+        strategy.trades[2].net_pnl = 0.0;
+        let quiet = &strategy.metrics_by_tag()["quiet"];
+        assert_eq!(quiet.total_trades, 1);
+        assert_eq!(quiet.win_rate, 100.0);
+    }
+
+    #[test]
+    fn test_split_back_adjusts_prior_prices() {
+        let adjustments = temp_path("adjustments.csv");
+        std::fs::write(&adjustments, "date,factor\n2024-01-16,0.5\n").unwrap();
+        let csv = "date,open,high,low,close,volume\n\
+                   2024-01-15 09:15:00,200,210,190,204,100\n\
+                   2024-01-16 09:15:00,102,106,98,104,200\n";
+
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_adjustments(adjustments.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&adjustments).ok();
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+
+        let ohlc: Vec<(f64, f64, f64, f64)> = strategy.data.iter()
+            .map(|bar| (bar.open, bar.high, bar.low, bar.close))
+            .collect();
+        assert_eq!(ohlc, vec![(100.0, 105.0, 95.0, 102.0), (102.0, 106.0, 98.0, 104.0)]);
+        assert_eq!(strategy.data[0].volume, 100.0);
+    }
+
+    #[test]
+    fn test_zero_transaction_cost_rate() {
+        let mut bars = breakout_day("2024-01-15", 113.0);
+        bars.extend(breakout_day("2024-01-16", 95.0));
+
+        let config = StrategyConfig { transaction_cost_rate: 0.0, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars.clone());
+        assert_eq!(strategy.trades.len(), 2);
+        assert!(strategy.trades.iter().all(|t| t.net_pnl == t.gross_pnl));
+
+        // The default rate still charges the legacy cost
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, bars);
+        assert!(strategy.trades.iter().all(|t| t.net_pnl < t.gross_pnl));
+    }
+
+    #[test]
+    fn test_range_bar_volume_and_close_time() {
+        let ticks: Vec<OhlcBar> = [
+            ("09:15:00", 100.0, 10.0),
+            ("09:15:01", 101.0, 5.0),
+            ("09:15:02", 102.0, 7.0),
+            ("09:15:03", 101.5, 3.0),
+            ("09:15:04", 100.0, 4.0),
+            ("09:15:05", 99.5, 1.0),
+            ("09:15:06", 100.0, 9.0),
+        ].iter().map(|&(time, price, volume)| {
+            let mut tick = test_bar(&format!("2024-01-15 {}", time), price, price, price, price);
+            tick.volume = volume;
+            tick
+        }).collect();
+
+        let bars = NiftyStrategy::create_range_bars(ticks.clone(), 2.0, VolumeAggregation::Sum);
+        let summary: Vec<(String, f64, f64, f64)> = bars.iter()
+            .map(|bar| (bar.time.to_string(), bar.open, bar.close, bar.volume))
+            .collect();
+        assert_eq!(summary, vec![
+            ("09:15:02".to_string(), 100.0, 102.0, 22.0),
+            ("09:15:05".to_string(), 101.5, 99.5, 8.0),
+            // Partial bar left when the session's ticks run out
+            ("09:15:06".to_string(), 100.0, 100.0, 9.0),
+        ]);
+
+        let bars = NiftyStrategy::create_range_bars(ticks, 2.0, VolumeAggregation::Last);
+        let volumes: Vec<f64> = bars.iter().map(|bar| bar.volume).collect();
+        assert_eq!(volumes, vec![7.0, 1.0, 9.0]);
+    }
+
+    #[test]
+    fn test_cost_models() {
+        // Long from 103 to 113 with the default 0.0012 rate
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+        let trade = &strategy.trades[0];
+        assert!((trade.gross_pnl - trade.net_pnl - 10.0 * 0.0012).abs() < 1e-9);
+
+        let config = StrategyConfig { cost_model: CostModel::Notional, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+        let trade = &strategy.trades[0];
+        assert!((trade.gross_pnl - trade.net_pnl - (103.0 + 113.0) * 0.0012).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_slippage_reduces_net_pnl() {
+        // A long winner, a long loser and a short on the bearish third day
+        let mut bearish = breakout_day("2024-01-17", 95.0);
+        bearish[0] = test_bar("2024-01-17 09:25:00", 101.0, 102.0, 99.0, 100.0);
+        bearish[1] = test_bar("2024-01-17 09:30:00", 100.0, 100.5, 97.5, 98.0);
+        let bars = [breakout_day("2024-01-15", 113.0), breakout_day("2024-01-16", 95.0), bearish].concat();
+
+        let mut baseline = NiftyStrategy::new();
+        run_pipeline(&mut baseline, bars.clone());
+        assert_eq!(baseline.trades.iter().map(|t| t.signal).collect::<Vec<_>>(), vec![1, 1, -1]);
+
+        for slippage in [SlippageModel::FixedPoints(0.5), SlippageModel::PercentOfPrice(0.1)] {
+            let config = StrategyConfig { slippage, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            run_pipeline(&mut strategy, bars.clone());
+            for (slipped, clean) in strategy.trades.iter().zip(&baseline.trades) {
+                assert!(slipped.net_pnl < clean.net_pnl, "{:?} {:?}", slippage, slipped);
+                // Fills are worse in the trade's direction on both legs
+                let direction = clean.signal as f64;
+                assert!(direction * (slipped.entry_price - clean.entry_price) > 0.0);
+                assert!(direction * (slipped.exit_price.unwrap() - clean.exit_price.unwrap()) < 0.0);
+            }
+        }
+
+        let config = StrategyConfig { slippage: SlippageModel::FixedPoints(0.5), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+        assert_eq!((strategy.trades[0].entry_price, strategy.trades[0].exit_price), (103.5, Some(112.5)));
+    }
+
+    #[test]
+    fn test_summary_precision() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades.push(test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 12.345));
+        strategy.trades.push(test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, -4.0));
+        let metrics = strategy.calculate_performance_metrics();
+
+        let summary = format_summary(&metrics, &strategy.config, 0.0);
+        assert!(summary.contains(&format!("Sharpe Ratio: {:.4}\n", metrics.sharpe_ratio)));
+        assert!(summary.contains("Total PnL: 8.35\n"));
+
+        let config = StrategyConfig { ratio_precision: 1, pnl_precision: 0, ..StrategyConfig::default() };
+        let summary = format_summary(&metrics, &config, 0.0);
+        assert!(summary.contains(&format!("Sharpe Ratio: {:.1}\n", metrics.sharpe_ratio)));
+        assert!(summary.contains("Total PnL: 8\n"));
+    }
+
+    #[test]
+    fn test_total_return_and_capital_cagr() {
+        let config = StrategyConfig { initial_capital: 1000.0, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        // 210 on 1000 over the 182 days from January 1st to July 1st 2024
+        strategy.trades = vec![
+            test_trade("2024-01-01", "2024-01-01 09:30:00", "2024-01-01 15:15:00", 1, 250.0),
+            test_trade("2024-07-01", "2024-07-01 09:30:00", "2024-07-01 15:15:00", 1, -40.0),
+        ];
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.total_return_pct - 21.0).abs() < 1e-9);
+        let expected_cagr = (1.21_f64.powf(365.25 / 182.0) - 1.0) * 100.0;
+        assert!((metrics.capital_cagr_pct - expected_cagr).abs() < 1e-9);
+        let summary = format_summary(&metrics, &strategy.config, 0.0);
+        assert!(summary.contains("Total Return: 21.00%"));
+        assert!(summary.contains(&format!("CAGR on Capital: {:.2}%", expected_cagr)));
+
+        // One trade has no span to annualize over
+        strategy.trades.truncate(1);
+        let metrics = strategy.calculate_performance_metrics();
+        assert!((metrics.total_return_pct - 25.0).abs() < 1e-9);
+        assert_eq!(metrics.capital_cagr_pct, 0.0);
+    }
+
+    #[test]
+    fn test_wick_rejection_signal() {
+        let bars = vec![
+            // Signal candle with a lower wick of 4.5 points on a 5 point range
+            test_bar("2024-01-15 09:25:00", 100.0, 100.5, 95.5, 100.2),
+            test_bar("2024-01-15 09:30:00", 100.2, 101.5, 100.0, 101.0),
+            // A later upper wick does not flip the day
+            test_bar("2024-01-15 09:35:00", 101.0, 105.0, 100.5, 100.8),
+            test_bar("2024-01-15 15:15:00", 103.0, 104.0, 102.0, 103.5),
+            // Marubozu signal candle: the rejection wick at 9:30 is not at signal time
+            test_bar("2024-01-16 09:25:00", 100.0, 102.0, 100.0, 102.0),
+            test_bar("2024-01-16 09:30:00", 102.0, 102.5, 97.5, 102.2),
+            test_bar("2024-01-16 09:35:00", 102.2, 103.0, 102.0, 102.5),
+            test_bar("2024-01-16 15:15:00", 103.0, 104.0, 102.0, 103.5),
+        ];
+        let config = StrategyConfig { signal_source: SignalSource::WickRejection, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        assert!(strategy.data[..4].iter().all(|bar| (bar.wick_ratio.unwrap() - 0.9).abs() < 1e-9 && bar.signal == 1));
+        assert!(strategy.data[4..].iter().all(|bar| bar.wick_ratio == Some(0.0) && bar.signal == 0));
+
+        assert_eq!(strategy.trades.len(), 1);
+        let trade = &strategy.trades[0];
+        assert_eq!((trade.signal, trade.entry_time.time(), trade.entry_price), (1, NaiveTime::from_hms_opt(9, 30, 0).unwrap(), 101.0));
+    }
+
+    #[test]
+    fn test_fill_gaps_inserts_flat_bars() {
+        let csv = "date,open,high,low,close,volume\n\
+                   2024-01-15 09:15:00,100,102,99,101,500\n\
+                   2024-01-15 09:20:00,101,103,100,102,600\n\
+                   2024-01-15 09:35:00,102,104,101,103,700\n\
+                   2024-01-16 09:15:00,103,104,102,103.5,800\n";
+        let config = StrategyConfig { fill_gaps: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+
+        let times: Vec<String> = strategy.data.iter().map(|bar| bar.datetime.to_string()).collect();
+        assert_eq!(times, vec![
+            "2024-01-15 09:15:00", "2024-01-15 09:20:00", "2024-01-15 09:25:00",
+            "2024-01-15 09:30:00", "2024-01-15 09:35:00", "2024-01-16 09:15:00",
+        ]);
+        let filler = &strategy.data[2];
+        assert!(filler.synthetic && strategy.data[3].synthetic);
+        assert_eq!((filler.open, filler.high, filler.low, filler.close, filler.volume), (102.0, 102.0, 102.0, 102.0, 0.0));
+        assert!(!strategy.data[4].synthetic);
+        // The overnight gap is left alone and the health report still sees the source gap
+        assert_eq!(strategy.data_health_report().unwrap().gaps, 1);
+    }
+
+    #[test]
+    fn test_monthly_and_yearly_pnl() {
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades = vec![
+            test_trade("2023-11-06", "2023-11-06 09:35:00", "2023-11-06 15:15:00", 1, 12.5),
+            test_trade("2023-11-20", "2023-11-20 09:35:00", "2023-11-20 15:15:00", -1, -4.0),
+            test_trade("2023-12-01", "2023-12-01 09:35:00", "2023-12-01 15:15:00", 1, 7.25),
+            test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, -3.0),
+            test_trade("2024-03-04", "2024-03-04 09:35:00", "2024-03-04 15:15:00", -1, 10.0),
+        ];
+
+        let monthly = strategy.monthly_pnl();
+        assert_eq!(monthly.into_iter().collect::<Vec<_>>(), vec![
+            ((2023, 11), 8.5), ((2023, 12), 7.25), ((2024, 1), -3.0), ((2024, 3), 10.0),
+        ]);
+        let yearly = strategy.yearly_pnl();
+        assert_eq!(yearly.into_iter().collect::<Vec<_>>(), vec![(2023, 15.75), (2024, 7.0)]);
+
+        let total_pnl = strategy.calculate_performance_metrics().total_pnl;
+        assert!((strategy.monthly_pnl().values().sum::<f64>() - total_pnl).abs() < 1e-9);
+        assert!((strategy.yearly_pnl().values().sum::<f64>() - total_pnl).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sharpe_std_ddof() {
+        let trades = vec![
+            test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, 1.0),
+            test_trade("2024-01-16", "2024-01-16 09:35:00", "2024-01-16 15:15:00", 1, 3.0),
+        ];
+        // Mean 2 with squared deviations summing to 2
+        let mut strategy = NiftyStrategy::new();
+        strategy.trades = trades.clone();
+        assert!((strategy.calculate_performance_metrics().sharpe_ratio - 2.0).abs() < 1e-9);
+
+        let config = StrategyConfig { std_ddof: 1, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.trades = trades;
+        assert!((strategy.calculate_performance_metrics().sharpe_ratio - 2.0 / 2.0_f64.sqrt()).abs() < 1e-9);
+
+        // A single trade has no sample deviation
+        strategy.trades.truncate(1);
+        assert_eq!(strategy.calculate_performance_metrics().sharpe_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_save_results_json_round_trip() {
+        let mut strategy = NiftyStrategy::new();
+        run_pipeline(&mut strategy, [breakout_day("2024-01-15", 113.0), breakout_day("2024-01-16", 95.0)].concat());
+
+        let path = temp_path("results.json");
+        strategy.save_results_json(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(contents.contains("\"entry_time\": \"2024-01-15T09:30:00\""));
+        assert!(contents.contains("\"date\": \"2024-01-15\""));
+        let document: ResultsDocument = serde_json::from_str(&contents).unwrap();
+        assert_eq!(document.trades.len(), 2);
+        for (parsed, trade) in document.trades.iter().zip(&strategy.trades) {
+            assert_eq!((parsed.date, parsed.entry_time, parsed.exit_time), (trade.date, trade.entry_time, trade.exit_time));
+            assert_eq!((parsed.net_pnl, parsed.exit_reason), (trade.net_pnl, trade.exit_reason));
+        }
+        let metrics = strategy.calculate_performance_metrics();
+        assert_eq!(document.metrics.total_pnl, metrics.total_pnl);
+        assert_eq!(document.metrics.total_trades, 2);
+
+        // PnL stays numeric rather than quoted
+        let raw: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert!(raw["trades"][0]["net_pnl"].is_f64());
+    }
+
+    #[test]
+    fn test_atr_target_scales_with_volatility() {
+        // Every bar has a true range of 2, so the ATR is 2 throughout
+        let bars = vec![
+            test_bar("2024-01-15 09:15:00", 101.0, 102.0, 100.0, 101.0),
+            test_bar("2024-01-15 09:20:00", 101.0, 102.0, 100.0, 101.0),
+            test_bar("2024-01-15 09:25:00", 100.5, 102.0, 100.0, 101.0),
+            test_bar("2024-01-15 09:30:00", 101.0, 103.0, 101.0, 102.5),
+            test_bar("2024-01-15 09:35:00", 102.5, 104.5, 102.5, 104.0),
+            test_bar("2024-01-15 09:40:00", 104.0, 106.0, 104.0, 105.5),
+            test_bar("2024-01-15 15:15:00", 105.0, 106.0, 104.0, 105.0),
+        ];
+        let config = StrategyConfig { atr_target_multiple: Some(1.5), atr_period: 3, ..StrategyConfig::default() };
+
+        let mut strategy = NiftyStrategy::with_config(config.clone());
+        run_pipeline(&mut strategy, bars.clone());
+        assert!(strategy.data.iter().skip(2).all(|bar| bar.atr == Some(2.0)));
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::TakeProfit);
+        assert_eq!((trade.entry_price, trade.exit_price), (102.5, Some(105.5)));
+
+        // Doubling every price doubles the ATR and the target distance with it
+        let doubled: Vec<OhlcBar> = bars.iter().map(|bar| OhlcBar {
+            open: bar.open * 2.0,
+            high: bar.high * 2.0,
+            low: bar.low * 2.0,
+            close: bar.close * 2.0,
+            ..bar.clone()
+        }).collect();
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, doubled);
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.exit_reason, ExitReason::TakeProfit);
+        assert_eq!((trade.entry_price, trade.exit_price), (205.0, Some(211.0)));
+    }
+
+    #[test]
+    fn test_trade_records_entry_and_exit_bars() {
+        let config = StrategyConfig { extended_output: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, breakout_day("2024-01-15", 113.0));
+
+        let trade = &strategy.trades[0];
+        assert_eq!(trade.entry_bar_ohlcv, BarOhlcv::from(&strategy.data[1]));
+        assert_eq!(trade.exit_bar_ohlcv, Some(BarOhlcv::from(&strategy.data[3])));
+        assert_eq!(trade.entry_bar_ohlcv, BarOhlcv { open: 101.0, high: 103.5, low: 100.5, close: 103.0, volume: 1000.0 });
+
+        let path = temp_path("extended.csv");
+        strategy.save_results(path.to_str().unwrap()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[0].ends_with(",exit_reason,entry_open,entry_high,entry_low,entry_close,entry_volume,\
+                                    exit_open,exit_high,exit_low,exit_close,exit_volume"));
+        assert!(lines[1].ends_with(",EndOfDay,101,103.5,100.5,103,1000,113,114,112,113,1000"));
+    }
+
+    #[test]
+    fn test_annualized_report_over_one_year() {
+        let config = StrategyConfig { initial_capital: 1000.0, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        assert!(NiftyStrategy::new().annualized_report().is_none());
+        // 1000 -> 1100 -> 1045 -> 1200 over the 365 days of 2023
+        strategy.trades = vec![
+            test_trade("2023-01-01", "2023-01-01 09:30:00", "2023-01-01 15:15:00", 1, 100.0),
+            test_trade("2023-06-01", "2023-06-01 09:30:00", "2023-06-01 15:15:00", 1, -55.0),
+            test_trade("2024-01-01", "2024-01-01 09:30:00", "2024-01-01 15:15:00", 1, 155.0),
+        ];
+        let report = strategy.annualized_report().unwrap();
+
+        assert!((report.total_return_pct - 20.0).abs() < 1e-9);
+        // A one-year span annualizes to the simple return, give or take the quarter leap day
+        assert!((report.annualized_return_pct - report.total_return_pct).abs() < 0.05);
+        assert!((report.max_drawdown_pct - 5.0).abs() < 1e-9);
+        assert!((report.calmar_ratio - report.annualized_return_pct / 5.0).abs() < 1e-9);
+
+        let returns = [0.1, -0.05, 155.0 / 1045.0];
+        let mean = returns.iter().sum::<f64>() / 3.0;
+        let std = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 3.0).sqrt();
+        assert!((report.annualized_volatility_pct - std * 252.0_f64.sqrt() * 100.0).abs() < 1e-9);
+        assert!((report.sharpe_ratio - report.annualized_return_pct / report.annualized_volatility_pct).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_metrics_finite_for_degenerate_trades() {
+        let all_finite = |m: &PerformanceMetrics| [
+            m.total_pnl, m.max_drawdown, m.sharpe_ratio, m.sortino_ratio, m.calmar_ratio, m.win_rate,
+            m.avg_win, m.avg_loss, m.exposure_pct, m.avg_bars_to_peak, m.expectancy_bps,
+            m.profitable_days_pct, m.cagr_pct, m.mar_ratio, m.total_return_pct, m.capital_cagr_pct,
+        ].iter().all(|v| v.is_finite());
+
+        let identical: Vec<Trade> = (15..19)
+            .map(|day| {
+                let date = format!("2024-01-{}", day);
+                test_trade(&date, &format!("{} 09:35:00", date), &format!("{} 15:15:00", date), 1, 5.0)
+            })
+            .collect();
+        let single = vec![test_trade("2024-01-15", "2024-01-15 09:35:00", "2024-01-15 15:15:00", 1, -3.0)];
+        let mut zero_price = single.clone();
+        zero_price[0].entry_price = 0.0;
+        let mut nan_pnl = identical.clone();
+        nan_pnl[1].net_pnl = f64::NAN;
+
+        for trades in [identical, single, zero_price, nan_pnl] {
+            let config = StrategyConfig { initial_capital: 1000.0, ..StrategyConfig::default() };
+            let mut strategy = NiftyStrategy::with_config(config);
+            strategy.trades = trades;
+            let metrics = strategy.calculate_performance_metrics();
+            assert!(all_finite(&metrics), "{:?}", metrics);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_signal_days_across_files() {
+        let mut second_file = breakout_day("2024-01-16", 95.0);
+        let mut overlap = breakout_day("2024-01-15", 113.0);
+        overlap[0] = test_bar("2024-01-15 09:25:00", 101.0, 102.5, 98.5, 100.0);
+        second_file.extend(overlap);
+        let first = write_minute_csv("merge_first.csv", &breakout_day("2024-01-15", 113.0));
+        let second = write_minute_csv("merge_second.csv", &second_file);
+        let paths = [first.to_str().unwrap(), second.to_str().unwrap()];
+
+        let mut strategy = NiftyStrategy::new();
+        let duplicates = strategy.load_and_prepare_files(&paths).unwrap();
+        assert_eq!(duplicates, vec![("NIFTY".to_string(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap())]);
+        // The first file's bullish candle is kept and each date appears once
+        let signal_bars: Vec<&OhlcBar> = strategy.data.iter().filter(|bar| bar.time == strategy.config.signal_time).collect();
+        assert_eq!(signal_bars.len(), 2);
+        assert_eq!(signal_bars[0].close, 101.0);
+
+        let config = StrategyConfig { duplicate_signal_policy: DataIssuePolicy::Error, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        let error = strategy.load_and_prepare_files(&paths).unwrap_err();
+        assert!(error.to_string().contains("differing signal candles across input files on 2024-01-15"));
+
+        std::fs::remove_file(&first).ok();
+        std::fs::remove_file(&second).ok();
+    }
+
+    #[test]
+    fn test_dedupe_duplicate_and_out_of_order_rows() {
+        // 09:16 is repeated verbatim and 09:18 arrives late; the differing 09:17 rows are kept
+        let csv = "date,open,high,low,close,volume\n\
+                   2024-01-15 09:15:00,100,101,99,100.5,10\n\
+                   2024-01-15 09:16:00,100.5,102,100,101.5,20\n\
+                   2024-01-15 09:16:00,100.5,102,100,101.5,20\n\
+                   2024-01-15 09:17:00,101.5,102,101,101.8,30\n\
+                   2024-01-15 09:17:00,101.5,102.2,101,101.9,5\n\
+                   2024-01-15 09:19:00,102,103,101.5,102.5,50\n\
+                   2024-01-15 09:18:00,101.8,102.5,101.2,102,40\n\
+                   2024-01-15 09:20:00,102.5,103.5,102,103,60\n\
+                   2024-01-15 09:16:00,100.5,102,100,101.5,20\n";
+
+        let config = StrategyConfig { dedupe_rows: true, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(strategy.data.len(), 2);
+        let first = &strategy.data[0];
+        assert_eq!((first.volume, first.source_count), (155.0, 6));
+        assert_eq!((first.open, first.high, first.close), (100.0, 103.0, 102.5));
+        assert_eq!(strategy.data[1].volume, 60.0);
+
+        // Without dedupe the repeats inflate the first bar's volume
+        let mut strategy = NiftyStrategy::new();
+        strategy.load_from_bytes(csv.as_bytes()).unwrap();
+        assert_eq!(strategy.data[0].volume, 195.0);
+
+        let config = StrategyConfig {
+            max_duplicate_rows: Some(1),
+            duplicate_rows_policy: DataIssuePolicy::Error,
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        let error = strategy.load_from_bytes(csv.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("2 duplicate rows found, above the limit of 1"));
+    }
+
+    #[test]
+    fn test_stop_scan_bounded_to_post_entry_bars() {
+        // Bars before the entry, the entry bar itself and a bar after the forced exit all
+        // trade through the 102 stop; none of them may trigger it
+        let mut bars = vec![test_bar("2024-01-15 09:15:00", 100.0, 101.0, 50.0, 100.0)];
+        bars.extend(breakout_day("2024-01-15", 113.0));
+        bars.push(test_bar("2024-01-15 15:20:00", 113.0, 113.0, 50.0, 60.0));
+        let config = StrategyConfig { stop_loss_points: Some(1.0), ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert_eq!(strategy.data[2].low, 100.5);
+        assert_eq!((trade.exit_reason, trade.exit_price), (ExitReason::EndOfDay, Some(113.0)));
+        assert_eq!(trade.bars_held, 2);
+        // The adverse excursion also only sees the 09:35 bar's 102.5 low
+        assert_eq!(strategy.session_adverse_excursion().unwrap().worst, 0.5);
+    }
+
+    #[test]
+    fn test_detect_and_fill_gaps() {
+        let bars = vec![
+            test_bar("2024-01-15 09:15:00", 100.0, 101.0, 99.5, 100.5),
+            test_bar("2024-01-15 09:20:00", 100.5, 101.5, 100.0, 101.0),
+            // 09:25 removed
+            test_bar("2024-01-15 09:30:00", 101.0, 102.0, 100.5, 101.5),
+            // Overnight, then across the lunch break
+            test_bar("2024-01-16 11:55:00", 101.5, 102.0, 101.0, 101.8),
+            test_bar("2024-01-16 13:00:00", 101.8, 102.5, 101.5, 102.0),
+            // Across the session close into after-hours prints
+            test_bar("2024-01-17 15:25:00", 102.0, 102.5, 101.5, 102.2),
+            test_bar("2024-01-17 15:40:00", 102.2, 102.4, 102.0, 102.1),
+        ];
+        let config = StrategyConfig {
+            session_breaks: vec![(NaiveTime::from_hms_opt(12, 0, 0).unwrap(), NaiveTime::from_hms_opt(13, 0, 0).unwrap())],
+            ..StrategyConfig::default()
+        };
+        let mut strategy = NiftyStrategy::with_config(config);
+        strategy.load_bars(bars.clone());
+
+        let dt = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(strategy.detect_gaps(), vec![(dt("2024-01-15 09:25:00"), dt("2024-01-15 09:30:00"))]);
+
+        let filled = strategy.fill_gaps(bars);
+        let synthetic: Vec<&OhlcBar> = filled.iter().filter(|bar| bar.synthetic).collect();
+        assert_eq!(synthetic.len(), 1);
+        assert_eq!(synthetic[0].datetime, dt("2024-01-15 09:25:00"));
+        assert_eq!((synthetic[0].open, synthetic[0].close, synthetic[0].volume), (101.0, 101.0, 0.0));
+        assert_eq!(filled.len(), 8);
+    }
+
+    #[test]
+    fn test_short_percent_return() {
+        // Short at the 98 close below the bearish candle's 99 low, covered at the 95 open
+        let mut bars = breakout_day("2024-01-17", 95.0);
+        bars[0] = test_bar("2024-01-17 09:25:00", 101.0, 102.0, 99.0, 100.0);
+        bars[1] = test_bar("2024-01-17 09:30:00", 100.0, 100.5, 97.5, 98.0);
+        let config = StrategyConfig { transaction_cost_rate: 0.0, ..StrategyConfig::default() };
+        let mut strategy = NiftyStrategy::with_config(config);
+        run_pipeline(&mut strategy, bars);
+
+        let trade = &strategy.trades[0];
+        assert_eq!((trade.signal, trade.entry_price, trade.exit_price), (-1, 98.0, Some(95.0)));
+        assert!((trade.return_pct() - 3.0 / 98.0).abs() < 1e-12);
+        assert!((strategy.percent_return_curve()[1] - (1.0 + 3.0 / 98.0)).abs() < 1e-12);
+
+        // A losing short is negative on the same base, and a short below zero keeps its sign
+        let mut loser = trade.clone();
+        loser.exit_price = Some(107.8);
+        loser.net_pnl = 98.0 - 107.8;
+        assert!((loser.return_pct() + 0.1).abs() < 1e-12);
+        let mut negative = trade.clone();
+        (negative.entry_price, negative.exit_price, negative.net_pnl) = (-4.0, Some(-5.0), 1.0);
+        assert!((negative.return_pct() - 0.25).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,19 @@
+use orb::{NiftyStrategy, StrategyConfig};
+
+#[test]
+fn test_pipeline_through_public_api() {
+    let csv = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/breakout_day.csv")).unwrap();
+    let config = StrategyConfig { transaction_cost_rate: 0.0, ..StrategyConfig::default() };
+    let mut strategy = NiftyStrategy::with_config(config);
+
+    strategy.load_from_bytes(&csv).unwrap();
+    strategy.identify_signal_candles().unwrap();
+    strategy.generate_trading_signals().unwrap();
+    strategy.identify_trades().unwrap();
+    let metrics = strategy.calculate_performance_metrics();
+
+    assert_eq!(strategy.trades.len(), 1);
+    assert_eq!((strategy.trades[0].entry_price, strategy.trades[0].exit_price), (103.0, Some(113.0)));
+    assert_eq!(metrics.total_trades, 1);
+    assert_eq!(metrics.total_pnl, 10.0);
+}
@@ -0,0 +1,64 @@
+use assert_cmd::Command;
+
+fn orb() -> Command {
+    assert_cmd::cargo::cargo_bin_cmd!("orb")
+}
+
+#[test]
+fn test_cli_runs_fixture() {
+    let input = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/breakout_day.csv");
+    let output = std::env::temp_dir().join(format!("orb_cli_{}_trades.csv", std::process::id()));
+
+    orb().args(["--input", input, "--output", output.to_str().unwrap()]).assert().success();
+
+    let trades = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_file(&output).ok();
+    let lines: Vec<&str> = trades.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("2024-01-15,2024-01-15 09:30:00,103"));
+}
+
+#[test]
+fn test_cli_missing_input_fails_cleanly() {
+    let assert = orb().args(["--input", "no_such_file.csv", "--output", "-"]).assert().failure();
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("Error: cannot open input file 'no_such_file.csv'"), "{}", stderr);
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_cli_output_dash_keeps_stdout_clean() {
+    let input = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/breakout_day.csv");
+    let output = std::env::temp_dir().join(format!("orb_cli_{}_stdout_trades.csv", std::process::id()));
+    orb().args(["--input", input, "--output", output.to_str().unwrap()]).assert().success();
+    let trades = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_file(&output).ok();
+
+    let assert = orb().args(["--input", input, "--output", "-"]).assert().success();
+    let result = assert.get_output();
+
+    // stdout is the same CSV the file run wrote, header plus the single trade
+    let stdout = String::from_utf8(result.stdout.clone()).unwrap();
+    assert_eq!(stdout, trades);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.starts_with("date,"));
+
+    // Progress and the summary only ever reach stderr
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    for line in ["Starting NIFTY Trading Strategy...", "PERFORMANCE SUMMARY", "First 5 Trades:"] {
+        assert!(stderr.contains(line), "{}", stderr);
+        assert!(!stdout.contains(line));
+    }
+    assert!(!stderr.contains("Trades saved to"));
+}
+
+#[test]
+fn test_cli_rejects_flag_without_value() {
+    for args in [vec!["--input"], vec!["--output", "--input", "data.csv"]] {
+        let assert = orb().args(&args).assert().failure();
+        let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+        assert!(stderr.contains(&format!("Error: {} requires a value", args[0])), "{}", stderr);
+        assert!(!stderr.contains("Starting NIFTY"));
+    }
+}